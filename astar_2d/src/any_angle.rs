@@ -0,0 +1,307 @@
+use crate::line_of_sight::has_line_of_sight;
+use crate::{get_cardinal_neighbor_coords, get_neighbor_coords};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Copy, Clone, PartialEq)]
+struct AnyAngleFrontierItem {
+    pub position: u32,
+    pub cost: f32,
+}
+
+impl Eq for AnyAngleFrontierItem {}
+
+impl Ord for AnyAngleFrontierItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| self.position.cmp(&other.position))
+    }
+}
+
+impl PartialOrd for AnyAngleFrontierItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[inline(always)]
+fn euclidean(from: u32, to: u32, width: u32) -> f32 {
+    let dx = (from % width) as f32 - (to % width) as f32;
+    let dy = (from / width) as f32 - (to / width) as f32;
+    (dx * dx + dy * dy).sqrt()
+}
+
+fn find_any_angle_path(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    lazy: bool,
+) -> Vec<u32> {
+    let mut frontier = BinaryHeap::with_capacity(grid.len());
+    let mut cost_so_far = vec![f32::INFINITY; grid.len()];
+    let mut came_from = vec![start; grid.len()];
+    let mut closed = vec![false; grid.len()];
+    cost_so_far[start as usize] = 0.0;
+    frontier.push(AnyAngleFrontierItem {
+        cost: euclidean(start, end, width),
+        position: start,
+    });
+    let neighbor_coord_getter = match cardinal_directions {
+        true => get_cardinal_neighbor_coords,
+        _ => get_neighbor_coords,
+    };
+    let mut found = false;
+    while let Some(AnyAngleFrontierItem {
+        position: current_position,
+        ..
+    }) = frontier.pop()
+    {
+        if closed[current_position as usize] {
+            continue;
+        }
+        if lazy
+            && !has_line_of_sight(
+                came_from[current_position as usize],
+                current_position,
+                grid,
+                width,
+                cardinal_directions,
+            )
+        {
+            // The parent was assumed visible when this tile was queued, fall
+            // back to the cheapest expanded neighbor instead.
+            let mut best_cost = f32::INFINITY;
+            for neighbor in neighbor_coord_getter(current_position, grid, width) {
+                if !closed[neighbor as usize] {
+                    continue;
+                }
+                let cost =
+                    cost_so_far[neighbor as usize] + euclidean(neighbor, current_position, width);
+                if cost < best_cost {
+                    best_cost = cost;
+                    came_from[current_position as usize] = neighbor;
+                }
+            }
+            cost_so_far[current_position as usize] = best_cost;
+        }
+        closed[current_position as usize] = true;
+        if current_position == end {
+            found = true;
+            break;
+        }
+        let parent = came_from[current_position as usize];
+        for neighbor in neighbor_coord_getter(current_position, grid, width) {
+            if closed[neighbor as usize] {
+                continue;
+            }
+            let (from, cost) =
+                if lazy || has_line_of_sight(parent, neighbor, grid, width, cardinal_directions) {
+                    (
+                        parent,
+                        cost_so_far[parent as usize] + euclidean(parent, neighbor, width),
+                    )
+                } else {
+                    (
+                        current_position,
+                        cost_so_far[current_position as usize]
+                            + euclidean(current_position, neighbor, width),
+                    )
+                };
+            if cost < cost_so_far[neighbor as usize] {
+                cost_so_far[neighbor as usize] = cost;
+                came_from[neighbor as usize] = from;
+                frontier.push(AnyAngleFrontierItem {
+                    cost: cost + euclidean(neighbor, end, width),
+                    position: neighbor,
+                });
+            }
+        }
+    }
+    let mut path: Vec<u32> = Vec::new();
+    if found {
+        let mut last = end;
+        loop {
+            path.push(last);
+            last = came_from[last as usize];
+            if last == start {
+                break;
+            }
+        }
+        path.reverse();
+    }
+    path
+}
+
+/// Theta*: like `find_path`, but waypoints only appear where the path turns,
+/// and consecutive waypoints are joined by a straight line of sight.
+///
+/// Tiles are only walkable or not. Their costs in `grid` are ignored and each
+/// segment costs its euclidean length, so use `find_path` where heavier tiles
+/// should be avoided.
+pub fn find_path_theta_star(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+) -> Vec<u32> {
+    find_any_angle_path(start, end, grid, width, cardinal_directions, false)
+}
+
+/// Lazy Theta*: returns the same kind of path as `find_path_theta_star`, but
+/// defers line of sight checks until a tile is expanded, so far fewer are made.
+/// Tile costs are likewise ignored apart from 0 marking walls.
+pub fn find_path_lazy_theta_star(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+) -> Vec<u32> {
+    find_any_angle_path(start, end, grid, width, cardinal_directions, true)
+}
+
+/// Converts `start` and the waypoints that follow it into tile-centre
+/// coordinates, giving the continuous line a unit should follow.
+pub fn path_to_polyline(start: u32, path: &[u32], width: u32) -> Vec<(f32, f32)> {
+    if path.is_empty() {
+        return vec![];
+    }
+    std::iter::once(&start)
+        .chain(path)
+        .map(|idx| ((idx % width) as f32 + 0.5, (idx / width) as f32 + 0.5))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test::Bencher;
+
+    #[test]
+    fn it_goes_straight_to_a_visible_end() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        assert_eq!(find_path_theta_star(0, 23, &grid, 5, false), vec![23]);
+        assert_eq!(find_path_lazy_theta_star(0, 23, &grid, 5, false), vec![23]);
+    }
+
+    #[test]
+    fn it_ignores_tile_costs() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            9, 9, 9,
+            1, 1, 1,
+        ];
+        assert_eq!(find_path_theta_star(1, 7, &grid, 3, false), vec![7]);
+        assert_eq!(find_path_lazy_theta_star(1, 7, &grid, 3, false), vec![7]);
+    }
+
+    #[test]
+    fn it_turns_around_walls() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let path = find_path_theta_star(0, 48, &grid, 7, false);
+        assert_eq!(path, vec![29, 38, 48]);
+        assert_eq!(find_path_lazy_theta_star(0, 48, &grid, 7, false), path);
+    }
+
+    #[test]
+    fn it_keeps_every_segment_in_line_of_sight() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        for cardinal_directions in [false, true] {
+            for lazy in [false, true] {
+                let path = find_any_angle_path(0, 24, &grid, 7, cardinal_directions, lazy);
+                assert_eq!(path.last(), Some(&24));
+                let mut from = 0;
+                for &to in &path {
+                    assert!(has_line_of_sight(from, to, &grid, 7, cardinal_directions));
+                    from = to;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_returns_no_path_if_path_impossible() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 0, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            0, 0, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        assert_eq!(find_path_theta_star(0, 48, &grid, 7, false), vec![]);
+        assert_eq!(find_path_lazy_theta_star(0, 48, &grid, 7, false), vec![]);
+    }
+
+    #[test]
+    fn it_converts_a_path_to_a_polyline() {
+        assert_eq!(
+            path_to_polyline(0, &[29, 48], 7),
+            vec![(0.5, 0.5), (1.5, 4.5), (6.5, 6.5)]
+        );
+        assert_eq!(path_to_polyline(0, &[], 7), vec![]);
+    }
+
+    #[bench]
+    fn bench_theta_star_avoids_walls(b: &mut Bencher) {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        b.iter(|| find_path_theta_star(0, 48, &grid, 7, false));
+    }
+
+    #[bench]
+    fn bench_lazy_theta_star_avoids_walls(b: &mut Bencher) {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        b.iter(|| find_path_lazy_theta_star(0, 48, &grid, 7, false));
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{get_cardinal_neighbor_coords, get_neighbor_coords, manhattan, FrontierItem};
+use smallvec::SmallVec;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+/// A path found by `AnytimeSearch`. Its cost is at most `suboptimality` times
+/// the cost of the cheapest path. As with `find_path`, a search from a tile to
+/// itself finds a path of just that tile, while no path at all is `None`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnytimePath {
+    pub path: Vec<u32>,
+    pub suboptimality: f32,
+}
+
+/// ARA*: a weighted A* that is rerun with a smaller weight every time it is
+/// advanced, reusing the previous search each time. Iterating yields
+/// successively better paths, ending with an optimal one or with nothing if no
+/// path exists.
+///
+/// Only astar_2d has an anytime search. The other crates have
+/// `find_path_weighted` for a single weighted pass.
+pub struct AnytimeSearch<'a> {
+    start: u32,
+    end: u32,
+    grid: &'a [u32],
+    width: u32,
+    neighbor_coord_getter: fn(u32, &[u32], u32) -> SmallVec<[u32; 8]>,
+    heuristic_weight: f32,
+    weight_step: f32,
+    frontier: BinaryHeap<FrontierItem>,
+    cost_so_far: Vec<u32>,
+    came_from: Vec<u32>,
+    in_frontier: Vec<bool>,
+    closed: Vec<bool>,
+    // Tiles improved after being expanded in the current pass, which are
+    // revisited in the next one.
+    inconsistent: Vec<u32>,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> AnytimeSearch<'a> {
+    /// Starts with `initial_weight` on the heuristic, which drops by
+    /// `weight_step` with each improvement until it reaches 1.
+    pub fn new(
+        start: u32,
+        end: u32,
+        grid: &'a [u32],
+        width: u32,
+        cardinal_directions: bool,
+        initial_weight: f32,
+        weight_step: f32,
+    ) -> Self {
+        let mut cost_so_far = vec![u32::MAX; grid.len()];
+        let mut in_frontier = vec![false; grid.len()];
+        cost_so_far[start as usize] = 0;
+        in_frontier[start as usize] = true;
+        let mut search = Self {
+            start,
+            end,
+            grid,
+            width,
+            neighbor_coord_getter: match cardinal_directions {
+                true => get_cardinal_neighbor_coords,
+                _ => get_neighbor_coords,
+            },
+            heuristic_weight: initial_weight.max(1.0),
+            weight_step,
+            frontier: BinaryHeap::with_capacity(grid.len()),
+            cost_so_far,
+            came_from: vec![start; grid.len()],
+            in_frontier,
+            closed: vec![false; grid.len()],
+            inconsistent: vec![],
+            started: false,
+            done: false,
+        };
+        search.frontier.push(FrontierItem {
+            cost: search.priority(start),
+            tie_breaker: 0,
+            position: start,
+        });
+        search
+    }
+
+    #[inline(always)]
+    fn heuristic(&self, idx: u32) -> u32 {
+        manhattan(
+            (self.end % self.width) as i32,
+            (self.end / self.width) as i32,
+            (idx % self.width) as i32,
+            (idx / self.width) as i32,
+        )
+    }
+
+    #[inline(always)]
+    fn priority(&self, idx: u32) -> u32 {
+        let heuristic = self.heuristic(idx) as f64 * self.heuristic_weight as f64;
+        self.cost_so_far[idx as usize].saturating_add(heuristic as u32)
+    }
+
+    fn improve_path(&mut self) {
+        while let Some(&FrontierItem {
+            position: current_position,
+            cost: priority,
+            ..
+        }) = self.frontier.peek()
+        {
+            // Entries are left behind when a tile is improved or requeued.
+            if !self.in_frontier[current_position as usize]
+                || priority != self.priority(current_position)
+            {
+                self.frontier.pop();
+                continue;
+            }
+            if self.cost_so_far[self.end as usize] <= priority {
+                break;
+            }
+            self.frontier.pop();
+            self.in_frontier[current_position as usize] = false;
+            self.closed[current_position as usize] = true;
+            let current_x = current_position % self.width;
+            let current_y = current_position / self.width;
+            for neighbor in (self.neighbor_coord_getter)(current_position, self.grid, self.width) {
+                let distance = manhattan(
+                    current_x as i32,
+                    current_y as i32,
+                    (neighbor % self.width) as i32,
+                    (neighbor / self.width) as i32,
+                );
+                // Steps that would overflow are skipped, as with
+                // `OverflowPolicy::Checked`.
+                let cost = match self.cost_so_far[current_position as usize]
+                    .checked_add(self.grid[neighbor as usize])
+                    .and_then(|cost| cost.checked_add(distance))
+                {
+                    Some(cost) => cost,
+                    None => continue,
+                };
+                if cost >= self.cost_so_far[neighbor as usize] {
+                    continue;
+                }
+                self.cost_so_far[neighbor as usize] = cost;
+                self.came_from[neighbor as usize] = current_position;
+                if self.closed[neighbor as usize] {
+                    self.inconsistent.push(neighbor);
+                } else {
+                    self.in_frontier[neighbor as usize] = true;
+                    self.frontier.push(FrontierItem {
+                        cost: self.priority(neighbor),
+                        tie_breaker: 0,
+                        position: neighbor,
+                    });
+                }
+            }
+        }
+    }
+
+    // Requeues the inconsistent tiles and reorders the frontier for the new
+    // weight, then forgets which tiles were expanded.
+    fn prepare_next_pass(&mut self) {
+        for idx in std::mem::take(&mut self.inconsistent) {
+            self.in_frontier[idx as usize] = true;
+        }
+        self.frontier.clear();
+        for idx in 0..self.grid.len() as u32 {
+            if self.in_frontier[idx as usize] {
+                self.frontier.push(FrontierItem {
+                    cost: self.priority(idx),
+                    tie_breaker: 0,
+                    position: idx,
+                });
+            }
+        }
+        self.closed.fill(false);
+    }
+
+    // The weight is an upper bound, but often a tighter one can be given by
+    // comparing against the lowest unweighted estimate left to explore.
+    fn suboptimality(&self) -> f32 {
+        let end_cost = self.cost_so_far[self.end as usize];
+        let lowest_estimate = (0..self.grid.len() as u32)
+            .filter(|&idx| self.in_frontier[idx as usize])
+            .chain(self.inconsistent.iter().copied())
+            .map(|idx| self.cost_so_far[idx as usize].saturating_add(self.heuristic(idx)))
+            .min()
+            .unwrap_or(end_cost);
+        match lowest_estimate >= end_cost {
+            true => 1.0,
+            _ => self
+                .heuristic_weight
+                .min(end_cost as f32 / lowest_estimate as f32),
+        }
+    }
+
+    fn path(&self) -> Vec<u32> {
+        let mut path: Vec<u32> = Vec::new();
+        let mut last = self.end;
+        loop {
+            path.push(last);
+            last = self.came_from[last as usize];
+            if last == self.start {
+                break;
+            }
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl Iterator for AnytimeSearch<'_> {
+    type Item = AnytimePath;
+
+    fn next(&mut self) -> Option<AnytimePath> {
+        if self.done {
+            return None;
+        }
+        if self.started {
+            self.heuristic_weight = (self.heuristic_weight - self.weight_step).max(1.0);
+            self.prepare_next_pass();
+        }
+        self.started = true;
+        self.improve_path();
+        if self.cost_so_far[self.end as usize] == u32::MAX {
+            self.done = true;
+            return None;
+        }
+        let suboptimality = self.suboptimality();
+        self.done = suboptimality <= 1.0 || self.weight_step <= 0.0;
+        Some(AnytimePath {
+            path: self.path(),
+            suboptimality,
+        })
+    }
+}
+
+/// Runs an `AnytimeSearch` until it finds an optimal path or `time_limit` has
+/// passed, and returns the best path found. The first path is always waited
+/// for, even if it takes longer than `time_limit`.
+#[allow(clippy::too_many_arguments)]
+pub fn find_path_anytime(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    initial_weight: f32,
+    weight_step: f32,
+    time_limit: Duration,
+) -> Option<AnytimePath> {
+    let started_at = Instant::now();
+    let mut best = None;
+    for path in AnytimeSearch::new(
+        start,
+        end,
+        grid,
+        width,
+        cardinal_directions,
+        initial_weight,
+        weight_step,
+    ) {
+        best = Some(path);
+        if started_at.elapsed() >= time_limit {
+            break;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    fn path_cost(start: u32, path: &[u32], grid: &[u32], width: u32) -> u32 {
+        let mut from = start;
+        let mut cost = 0;
+        for &to in path {
+            cost += grid[to as usize]
+                + manhattan(
+                    (from % width) as i32,
+                    (from / width) as i32,
+                    (to % width) as i32,
+                    (to / width) as i32,
+                );
+            from = to;
+        }
+        cost
+    }
+
+    #[test]
+    fn it_improves_paths_until_optimal() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 9, 0, 1,
+            1, 1, 0, 0, 9, 0, 1,
+            1, 1, 1, 1, 9, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let paths: Vec<AnytimePath> = AnytimeSearch::new(0, 48, &grid, 7, true, 5.0, 1.0).collect();
+        assert!(!paths.is_empty());
+        let optimal = find_path(0, 48, &grid, 7, true);
+        let optimal_cost = path_cost(0, &optimal, &grid, 7);
+        let mut previous_cost = u32::MAX;
+        for AnytimePath {
+            path,
+            suboptimality,
+        } in &paths
+        {
+            let cost = path_cost(0, path, &grid, 7);
+            assert!(cost <= previous_cost);
+            assert!(cost as f32 <= optimal_cost as f32 * suboptimality);
+            assert_eq!(path.last(), Some(&48));
+            previous_cost = cost;
+        }
+        let last = paths.last().unwrap();
+        assert_eq!(last.suboptimality, 1.0);
+        assert_eq!(path_cost(0, &last.path, &grid, 7), optimal_cost);
+    }
+
+    #[test]
+    fn it_finds_nothing_if_path_impossible() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 0, 1,
+            1, 1, 0, 1,
+            0, 0, 0, 1,
+        ];
+        assert_eq!(
+            AnytimeSearch::new(0, 3, &grid, 4, false, 3.0, 1.0).next(),
+            None
+        );
+        assert_eq!(
+            find_path_anytime(0, 3, &grid, 4, false, 3.0, 1.0, Duration::from_secs(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn it_returns_the_best_path_within_the_time_limit() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        let found = find_path_anytime(0, 14, &grid, 5, false, 3.0, 0.5, Duration::from_secs(1));
+        let found = found.unwrap();
+        assert_eq!(found.suboptimality, 1.0);
+        assert_eq!(found.path, find_path(0, 14, &grid, 5, false));
+    }
+
+    #[test]
+    fn it_returns_the_end_when_already_there() {
+        let grid = vec![1; 9];
+        assert_eq!(
+            AnytimeSearch::new(4, 4, &grid, 3, false, 3.0, 1.0).collect::<Vec<_>>(),
+            vec![AnytimePath {
+                path: vec![4],
+                suboptimality: 1.0
+            }]
+        );
+        assert_eq!(find_path(4, 4, &grid, 3, false), vec![4]);
+    }
+
+    #[test]
+    fn it_skips_steps_that_overflow() {
+        let grid = vec![1, u32::MAX, 1];
+        assert_eq!(
+            AnytimeSearch::new(0, 2, &grid, 3, true, 3.0, 1.0).next(),
+            None
+        );
+        // The expensive tile is queued but never expanded, and its estimate
+        // overflows when the suboptimality of a path is worked out.
+        let mut grid = vec![1; 24];
+        grid[13] = u32::MAX - 5;
+        let last = AnytimeSearch::new(0, 11, &grid, 12, false, 3.0, 1.0)
+            .last()
+            .unwrap();
+        assert_eq!(last.suboptimality, 1.0);
+        assert_eq!(last.path, find_path(0, 11, &grid, 12, false));
+    }
+}
@@ -0,0 +1,219 @@
+//! A text format for writing maps by hand, and for drawing the paths found on
+//! them.
+//!
+//! Each character is a tile: `.` is floor, `#` is a wall and the digits `1`
+//! to `9` are tiles with that cost. `S` and `E` are floor tiles marking where
+//! a path starts and ends. Blank lines before and after the map and leading
+//! whitespace are ignored, so maps can be indented to match the code around
+//! them.
+//!
+//! ```text
+//! S.#.
+//! .#..
+//! ..#E
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    WrongRowWidth { row: u32, expected: u32, found: u32 },
+    UnknownTile { row: u32, column: u32, tile: char },
+    DuplicateMarker(char),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "the map has no tiles"),
+            ParseError::WrongRowWidth {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} is {} tiles wide rather than {}",
+                row, found, expected
+            ),
+            ParseError::UnknownTile { row, column, tile } => write!(
+                f,
+                "unknown tile `{}` at row {} column {}",
+                tile, row, column
+            ),
+            ParseError::DuplicateMarker(marker) => write!(f, "`{}` appears more than once", marker),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// A parsed map, ready to pass to `find_path` and the other searches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AsciiMap {
+    pub grid: Vec<u32>,
+    pub width: u32,
+    pub height: u32,
+    pub start: Option<u32>,
+    pub end: Option<u32>,
+}
+
+impl FromStr for AsciiMap {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = text.lines().map(str::trim).collect();
+        let first = lines.iter().position(|line| !line.is_empty());
+        let last = lines.iter().rposition(|line| !line.is_empty());
+        let rows = match (first, last) {
+            (Some(first), Some(last)) => &lines[first..=last],
+            _ => return Err(ParseError::Empty),
+        };
+        let width = rows[0].chars().count() as u32;
+        let mut map = AsciiMap {
+            grid: Vec::with_capacity(width as usize * rows.len()),
+            width,
+            height: rows.len() as u32,
+            start: None,
+            end: None,
+        };
+        for (row, line) in rows.iter().enumerate() {
+            let row = row as u32;
+            let found = line.chars().count() as u32;
+            if found != width {
+                return Err(ParseError::WrongRowWidth {
+                    row,
+                    expected: width,
+                    found,
+                });
+            }
+            for (column, tile) in line.chars().enumerate() {
+                let idx = map.grid.len() as u32;
+                let cost = match tile {
+                    '.' => 1,
+                    '#' => 0,
+                    '1'..='9' => tile.to_digit(10).unwrap(),
+                    'S' | 'E' => {
+                        let marker = match tile {
+                            'S' => &mut map.start,
+                            _ => &mut map.end,
+                        };
+                        if marker.replace(idx).is_some() {
+                            return Err(ParseError::DuplicateMarker(tile));
+                        }
+                        1
+                    }
+                    _ => {
+                        return Err(ParseError::UnknownTile {
+                            row,
+                            column: column as u32,
+                            tile,
+                        })
+                    }
+                };
+                map.grid.push(cost);
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl AsciiMap {
+    /// Draws the map back in the format it is parsed from, with `start`
+    /// marked `S`, the last tile of `path` marked `E` and the rest of `path`
+    /// marked `*`. Costs above 9 are drawn as `+`.
+    pub fn render_path(&self, start: u32, path: &[u32]) -> String {
+        let mut tiles: Vec<char> = self
+            .grid
+            .iter()
+            .map(|&cost| match cost {
+                0 => '#',
+                1 => '.',
+                2..=9 => char::from_digit(cost, 10).unwrap(),
+                _ => '+',
+            })
+            .collect();
+        for &idx in path {
+            tiles[idx as usize] = '*';
+        }
+        if let Some(&end) = path.last() {
+            tiles[end as usize] = 'E';
+        }
+        tiles[start as usize] = 'S';
+        tiles
+            .chunks(self.width as usize)
+            .map(|row| row.iter().collect::<String>() + "\n")
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    #[test]
+    fn it_parses_maps() {
+        let map: AsciiMap = "
+            S.#
+            .2E
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(map.grid, vec![1, 1, 0, 1, 2, 1]);
+        assert_eq!((map.width, map.height), (3, 2));
+        assert_eq!(map.start, Some(0));
+        assert_eq!(map.end, Some(5));
+    }
+
+    #[test]
+    fn it_rejects_malformed_maps() {
+        assert_eq!("\n \n".parse::<AsciiMap>(), Err(ParseError::Empty));
+        assert_eq!(
+            "...\n\n...\n".parse::<AsciiMap>(),
+            Err(ParseError::WrongRowWidth {
+                row: 1,
+                expected: 3,
+                found: 0
+            })
+        );
+        assert_eq!(
+            ".?\n".parse::<AsciiMap>(),
+            Err(ParseError::UnknownTile {
+                row: 0,
+                column: 1,
+                tile: '?'
+            })
+        );
+        assert_eq!(
+            "E.E\n".parse::<AsciiMap>(),
+            Err(ParseError::DuplicateMarker('E'))
+        );
+    }
+
+    #[test]
+    fn it_renders_paths() {
+        let map: AsciiMap = "
+            S......
+            ..#..#.
+            ..##.#.
+            ..#..#.
+            ..####.
+            .......
+            ......E
+        "
+        .parse()
+        .unwrap();
+        let (start, end) = (map.start.unwrap(), map.end.unwrap());
+        let path = find_path(start, end, &map.grid, map.width, true);
+        assert_eq!(
+            map.render_path(start, &path),
+            "S......\n*.#..#.\n*.##.#.\n*.#..#.\n*.####.\n*......\n******E\n"
+        );
+        let unmarked = AsciiMap { end: None, ..map };
+        assert_eq!(unmarked.render_path(start, &[]).parse(), Ok(unmarked));
+    }
+}
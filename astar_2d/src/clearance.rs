@@ -0,0 +1,117 @@
+use crate::find_path_with_edge_cost;
+
+// True clearance: the size of the largest open square whose top left corner is
+// `idx`, capped at `max_clearance`. Relies on the tiles to the right of and
+// below `idx` already being filled in.
+#[inline(always)]
+fn get_clearance(idx: u32, grid: &[u32], width: u32, max_clearance: u32, clearance: &[u32]) -> u32 {
+    if grid[idx as usize] == 0 {
+        return 0;
+    }
+    let is_right = idx % width == width - 1;
+    let is_bottom = idx as usize >= grid.len() - width as usize;
+    if is_right || is_bottom {
+        return max_clearance.min(1);
+    }
+    let right = clearance[idx as usize + 1];
+    let bottom = clearance[(idx + width) as usize];
+    let bottom_right = clearance[(idx + width) as usize + 1];
+    max_clearance.min(right.min(bottom).min(bottom_right) + 1)
+}
+
+/// Builds a clearance map for `find_path_with_clearance`. Each tile holds the
+/// size of the largest square agent that fits with its top left corner on
+/// that tile, or 0 for walls. Values are capped at `max_clearance`, which only
+/// needs to be as large as the biggest agent.
+pub fn create_clearance_map(grid: &[u32], width: u32, max_clearance: u32) -> Vec<u32> {
+    let mut clearance = vec![0; grid.len()];
+    for idx in (0..grid.len() as u32).rev() {
+        clearance[idx as usize] = get_clearance(idx, grid, width, max_clearance, &clearance);
+    }
+    clearance
+}
+
+/// Like `find_path`, but for a square agent `agent_size` tiles across whose
+/// top left corner is the tile being pathed. Only tiles with at least that
+/// much clearance are used.
+pub fn find_path_with_clearance(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    clearance: &[u32],
+    agent_size: u32,
+) -> Vec<u32> {
+    find_path_with_edge_cost(start, end, grid, width, cardinal_directions, |_, idx| {
+        (clearance[idx as usize] >= agent_size).then(|| grid[idx as usize])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    #[test]
+    fn it_creates_a_clearance_map() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1,
+            1, 1, 1, 0,
+            1, 1, 1, 1,
+            0, 1, 1, 1,
+        ];
+        #[rustfmt::skip]
+        let expected = vec![
+            3, 2, 1, 1,
+            2, 2, 1, 0,
+            1, 2, 2, 1,
+            0, 1, 1, 1,
+        ];
+        assert_eq!(create_clearance_map(&grid, 4, 4), expected);
+        #[rustfmt::skip]
+        let capped = vec![
+            2, 2, 1, 1,
+            2, 2, 1, 0,
+            1, 2, 2, 1,
+            0, 1, 1, 1,
+        ];
+        assert_eq!(create_clearance_map(&grid, 4, 2), capped);
+    }
+
+    #[test]
+    fn it_keeps_large_agents_out_of_narrow_gaps() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            0, 0, 1, 0, 0, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let clearance = create_clearance_map(&grid, 7, 2);
+        let path = find_path_with_clearance(0, 21, &grid, 7, false, &clearance, 1);
+        assert_eq!(path, find_path(0, 21, &grid, 7, false));
+        assert!(path.contains(&16));
+        let path = find_path_with_clearance(0, 21, &grid, 7, false, &clearance, 2);
+        assert!(path.contains(&19));
+        assert!(path.iter().all(|&idx| clearance[idx as usize] >= 2));
+        assert_eq!(path.last(), Some(&21));
+    }
+
+    #[test]
+    fn it_returns_no_path_if_the_agent_cannot_fit() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            0, 0, 1, 0, 0,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        let clearance = create_clearance_map(&grid, 5, 2);
+        let path = find_path_with_clearance(0, 20, &grid, 5, false, &clearance, 2);
+        assert_eq!(path, vec![]);
+    }
+}
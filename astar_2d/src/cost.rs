@@ -0,0 +1,201 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+/// A type path costs can be accumulated in, as returned by the closure given
+/// to `find_path_with_edge_cost`. Costs are never negative.
+pub trait Cost: Copy + Ord + Debug {
+    const ZERO: Self;
+    const MAX: Self;
+
+    /// Converts a distance in tiles, rounding down and clamping to `MAX`
+    /// where the type can't represent it exactly.
+    fn from_tiles(tiles: f64) -> Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// A key that sorts the same way as the cost, used for tie-breaking.
+    fn order_key(self) -> u64;
+}
+
+macro_rules! impl_cost_for_unsigned {
+    ($($cost:ty),*) => {
+        $(
+            impl Cost for $cost {
+                const ZERO: Self = 0;
+                const MAX: Self = <$cost>::MAX;
+
+                #[inline(always)]
+                fn from_tiles(tiles: f64) -> Self {
+                    tiles as $cost
+                }
+
+                #[inline(always)]
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$cost>::checked_add(self, other)
+                }
+
+                #[inline(always)]
+                fn saturating_add(self, other: Self) -> Self {
+                    <$cost>::saturating_add(self, other)
+                }
+
+                #[inline(always)]
+                fn order_key(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_cost_for_unsigned!(u16, u32, u64);
+
+/// An unsigned fixed-point cost with 16 fractional bits, for fractional tile
+/// weights with the same results on every platform.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fixed(pub u32);
+
+impl Fixed {
+    pub const FRACTION_BITS: u32 = 16;
+    pub const ONE: Fixed = Fixed(1 << Self::FRACTION_BITS);
+
+    pub fn from_f64(value: f64) -> Self {
+        Fixed((value * (1 << Self::FRACTION_BITS) as f64) as u32)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / (1 << Self::FRACTION_BITS) as f64
+    }
+}
+
+impl Cost for Fixed {
+    const ZERO: Self = Fixed(0);
+    const MAX: Self = Fixed(u32::MAX);
+
+    #[inline(always)]
+    fn from_tiles(tiles: f64) -> Self {
+        Fixed::from_f64(tiles)
+    }
+
+    #[inline(always)]
+    fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Fixed)
+    }
+
+    #[inline(always)]
+    fn saturating_add(self, other: Self) -> Self {
+        Fixed(self.0.saturating_add(other.0))
+    }
+
+    #[inline(always)]
+    fn order_key(self) -> u64 {
+        self.0 as u64
+    }
+}
+
+/// A floating point cost ordered with `f64::total_cmp`, as `find_octile_path`
+/// uses for its √2 diagonal steps. Results can differ between platforms that
+/// round floating point differently.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrderedFloat(pub f64);
+
+impl Eq for OrderedFloat {}
+
+impl Ord for OrderedFloat {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl PartialOrd for OrderedFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Cost for OrderedFloat {
+    const ZERO: Self = OrderedFloat(0.0);
+    const MAX: Self = OrderedFloat(f64::MAX);
+
+    #[inline(always)]
+    fn from_tiles(tiles: f64) -> Self {
+        OrderedFloat(tiles)
+    }
+
+    #[inline(always)]
+    fn checked_add(self, other: Self) -> Option<Self> {
+        let sum = self.0 + other.0;
+        sum.is_finite().then_some(OrderedFloat(sum))
+    }
+
+    #[inline(always)]
+    fn saturating_add(self, other: Self) -> Self {
+        self.checked_add(other).unwrap_or(Self::MAX)
+    }
+
+    #[inline(always)]
+    fn order_key(self) -> u64 {
+        // The bits of a non-negative float sort the same as its value.
+        self.0.to_bits()
+    }
+}
+
+/// What to do when adding up a path's cost overflows the cost type, set with
+/// `SearchOptions::overflow`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverflowPolicy {
+    /// Steps that would overflow are skipped, as if they were walls.
+    #[default]
+    Checked,
+    /// Costs stop at the cost type's maximum, so overflowing paths can still
+    /// be found but are no longer told apart.
+    Saturating,
+}
+
+impl OverflowPolicy {
+    #[inline(always)]
+    pub(crate) fn add<C: Cost>(self, cost: C, other: C) -> Option<C> {
+        match self {
+            OverflowPolicy::Checked => cost.checked_add(other),
+            OverflowPolicy::Saturating => Some(cost.saturating_add(other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_adds_with_either_policy() {
+        assert_eq!(OverflowPolicy::Checked.add(1u16, 2), Some(3));
+        assert_eq!(OverflowPolicy::Checked.add(u16::MAX, 1), None);
+        assert_eq!(OverflowPolicy::Saturating.add(u16::MAX, 1), Some(u16::MAX));
+        assert_eq!(OverflowPolicy::Checked.add(Fixed::MAX, Fixed::ONE), None);
+        assert_eq!(
+            OverflowPolicy::Saturating.add(OrderedFloat(f64::MAX), OrderedFloat(f64::MAX)),
+            Some(OrderedFloat::MAX)
+        );
+    }
+
+    #[test]
+    fn it_converts_tiles_to_costs() {
+        assert_eq!(u32::from_tiles(2.5), 2);
+        assert_eq!(u16::from_tiles(1e9), u16::MAX);
+        assert_eq!(Fixed::from_tiles(2.5), Fixed(5 << 15));
+        assert_eq!(Fixed::from_tiles(2.5).to_f64(), 2.5);
+        assert_eq!(OrderedFloat::from_tiles(2.5), OrderedFloat(2.5));
+    }
+
+    #[test]
+    fn it_keeps_the_order_of_costs() {
+        assert!(OrderedFloat(0.5).order_key() < OrderedFloat(1.5).order_key());
+        assert!(Fixed::from_f64(0.5).order_key() < Fixed::ONE.order_key());
+        assert!(OrderedFloat(0.5) < OrderedFloat(1.5));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::find_path_with_edge_cost;
+
+pub const NORTH: u8 = 1;
+pub const NORTH_EAST: u8 = 1 << 1;
+pub const EAST: u8 = 1 << 2;
+pub const SOUTH_EAST: u8 = 1 << 3;
+pub const SOUTH: u8 = 1 << 4;
+pub const SOUTH_WEST: u8 = 1 << 5;
+pub const WEST: u8 = 1 << 6;
+pub const NORTH_WEST: u8 = 1 << 7;
+pub const ALL_EXITS: u8 = u8::MAX;
+
+/// The exit bit for stepping from `from` to the adjacent tile `to`, or 0 if
+/// they are the same tile.
+#[inline(always)]
+pub fn get_exit_direction(from: u32, to: u32, width: u32) -> u8 {
+    let dx = (to % width) as i32 - (from % width) as i32;
+    let dy = (to / width) as i32 - (from / width) as i32;
+    match (dx.signum(), dy.signum()) {
+        (0, -1) => NORTH,
+        (1, -1) => NORTH_EAST,
+        (1, 0) => EAST,
+        (1, 1) => SOUTH_EAST,
+        (0, 1) => SOUTH,
+        (-1, 1) => SOUTH_WEST,
+        (-1, 0) => WEST,
+        (-1, -1) => NORTH_WEST,
+        _ => 0,
+    }
+}
+
+/// Like `find_path`, but a tile can only be left in the directions set in its
+/// `exits` bitmask, which allows one-way doors, conveyors and ledges.
+pub fn find_path_with_exits(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    exits: &[u8],
+) -> Vec<u32> {
+    find_path_with_edge_cost(start, end, grid, width, cardinal_directions, |from, to| {
+        let direction = get_exit_direction(from, to, width);
+        (exits[from as usize] & direction == direction).then(|| grid[to as usize])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    #[test]
+    fn it_gets_exit_directions() {
+        assert_eq!(get_exit_direction(4, 1, 3), NORTH);
+        assert_eq!(get_exit_direction(4, 2, 3), NORTH_EAST);
+        assert_eq!(get_exit_direction(4, 5, 3), EAST);
+        assert_eq!(get_exit_direction(4, 8, 3), SOUTH_EAST);
+        assert_eq!(get_exit_direction(4, 7, 3), SOUTH);
+        assert_eq!(get_exit_direction(4, 6, 3), SOUTH_WEST);
+        assert_eq!(get_exit_direction(4, 3, 3), WEST);
+        assert_eq!(get_exit_direction(4, 0, 3), NORTH_WEST);
+        assert_eq!(get_exit_direction(4, 4, 3), 0);
+    }
+
+    #[test]
+    fn it_only_leaves_tiles_through_their_exits() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        let all_exits = vec![ALL_EXITS; grid.len()];
+        assert_eq!(
+            find_path_with_exits(5, 9, &grid, 5, true, &all_exits),
+            find_path(5, 9, &grid, 5, true)
+        );
+        // The middle row is a conveyor that only runs east.
+        let mut exits = all_exits.clone();
+        exits[5..10].fill(EAST);
+        assert_eq!(
+            find_path_with_exits(5, 9, &grid, 5, true, &exits),
+            vec![6, 7, 8, 9]
+        );
+        let path = find_path_with_exits(9, 5, &grid, 5, true, &exits);
+        assert_eq!(path, vec![]);
+        let path = find_path_with_exits(14, 10, &grid, 5, true, &exits);
+        assert!(path.iter().all(|&idx| idx >= 10));
+        assert_eq!(path.last(), Some(&10));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{get_cardinal_neighbor_coords, get_neighbor_coords, manhattan, FrontierItem};
+use std::collections::BinaryHeap;
+
+/// The direction of travel, in 45 degree steps clockwise from north.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Heading {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+const HEADINGS: [Heading; 8] = [
+    Heading::North,
+    Heading::NorthEast,
+    Heading::East,
+    Heading::SouthEast,
+    Heading::South,
+    Heading::SouthWest,
+    Heading::West,
+    Heading::NorthWest,
+];
+
+impl Heading {
+    /// The heading of a step between two adjacent tiles.
+    pub fn from_step(from: u32, to: u32, width: u32) -> Self {
+        let dx = (to % width) as i32 - (from % width) as i32;
+        let dy = (to / width) as i32 - (from / width) as i32;
+        match (dx.signum(), dy.signum()) {
+            (0, -1) => Heading::North,
+            (1, -1) => Heading::NorthEast,
+            (1, 0) => Heading::East,
+            (1, 1) => Heading::SouthEast,
+            (0, 1) => Heading::South,
+            (-1, 1) => Heading::SouthWest,
+            (-1, 0) => Heading::West,
+            _ => Heading::NorthWest,
+        }
+    }
+
+    /// How many 45 degree steps it takes to turn from `self` to `other`, from
+    /// 0 for no turn to 4 for turning around.
+    pub fn turns_to(self, other: Heading) -> u8 {
+        let difference = (self as u8).abs_diff(other as u8);
+        difference.min(8 - difference)
+    }
+}
+
+/// Turning rules for `find_path_with_headings`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TurnRules {
+    /// Added to the cost of a step for every 45 degrees turned.
+    pub cost_per_turn: u32,
+    /// The sharpest turn allowed in a single step, in 45 degree steps. 4
+    /// allows turning around on the spot.
+    pub max_turn: u8,
+    /// Straight steps required after a turn before turning again, which
+    /// together with `max_turn` approximates a minimum turning radius.
+    pub min_straight_steps: u8,
+}
+
+impl Default for TurnRules {
+    fn default() -> Self {
+        Self {
+            cost_per_turn: 0,
+            max_turn: 4,
+            min_straight_steps: 0,
+        }
+    }
+}
+
+const NO_PARENT: usize = usize::MAX;
+
+/// Like `find_path`, but the search state is a tile and a heading, so turning
+/// can be penalised or restricted by `rules`. Without a `start_heading` the
+/// first step may go in any direction for free. Each step of the returned
+/// path carries the heading it was taken in. When `start` is `end` the path is
+/// just `end`, facing `start_heading` or else north, so it can be told apart
+/// from there being no path.
+pub fn find_path_with_headings(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    start_heading: Option<Heading>,
+    rules: &TurnRules,
+) -> Vec<(u32, Heading)> {
+    if start == end {
+        return vec![(end, start_heading.unwrap_or(Heading::North))];
+    }
+    // States are laid out as tile, then heading, then straight steps taken
+    // since the last turn, capped at `min_straight_steps`. There are several
+    // per tile, so they are counted in `usize` even when tiles fit in `u32`.
+    let straight_states = rules.min_straight_steps as usize + 1;
+    let to_state = |tile: u32, heading: Heading, straight: usize| {
+        (tile as usize * 8 + heading as usize) * straight_states + straight
+    };
+    let to_tile = |state: usize| (state / straight_states / 8) as u32;
+    let state_count = grid.len() * 8 * straight_states;
+    let end_x = end % width;
+    let end_y = end / width;
+    let mut frontier = BinaryHeap::with_capacity(grid.len());
+    let mut cost_so_far = vec![u32::MAX; state_count];
+    let mut came_from = vec![NO_PARENT; state_count];
+    let mut closed = vec![false; state_count];
+    let start_headings = match start_heading {
+        Some(heading) => vec![heading],
+        None => HEADINGS.to_vec(),
+    };
+    for heading in start_headings {
+        let state = to_state(start, heading, straight_states - 1);
+        cost_so_far[state] = 0;
+        frontier.push(FrontierItem {
+            cost: 0,
+            tie_breaker: 0,
+            position: state,
+        });
+    }
+    let neighbor_coord_getter = match cardinal_directions {
+        true => get_cardinal_neighbor_coords,
+        _ => get_neighbor_coords,
+    };
+    let mut found = None;
+    while let Some(FrontierItem {
+        position: state, ..
+    }) = frontier.pop()
+    {
+        if closed[state] {
+            continue;
+        }
+        closed[state] = true;
+        let current_position = to_tile(state);
+        if current_position == end {
+            found = Some(state);
+            break;
+        }
+        let heading = HEADINGS[state / straight_states % 8];
+        let straight = state % straight_states;
+        let current_x = current_position % width;
+        let current_y = current_position / width;
+        for neighbor in neighbor_coord_getter(current_position, grid, width) {
+            let next_heading = Heading::from_step(current_position, neighbor, width);
+            let turns = heading.turns_to(next_heading);
+            if turns > rules.max_turn || (turns > 0 && straight < straight_states - 1) {
+                continue;
+            }
+            let next_straight = match turns {
+                0 => (straight + 1).min(straight_states - 1),
+                _ => 0,
+            };
+            let next_state = to_state(neighbor, next_heading, next_straight);
+            if closed[next_state] {
+                continue;
+            }
+            let neighbor_x = neighbor % width;
+            let neighbor_y = neighbor / width;
+            let distance = manhattan(
+                current_x as i32,
+                current_y as i32,
+                neighbor_x as i32,
+                neighbor_y as i32,
+            );
+            // Steps that would overflow are skipped, as with
+            // `OverflowPolicy::Checked`.
+            let cost = match (turns as u32)
+                .checked_mul(rules.cost_per_turn)
+                .and_then(|turn_cost| turn_cost.checked_add(cost_so_far[state]))
+                .and_then(|cost| cost.checked_add(grid[neighbor as usize]))
+                .and_then(|cost| cost.checked_add(distance))
+            {
+                Some(cost) => cost,
+                None => continue,
+            };
+            if cost < cost_so_far[next_state] {
+                cost_so_far[next_state] = cost;
+                came_from[next_state] = state;
+                let priority = cost.saturating_add(manhattan(
+                    end_x as i32,
+                    end_y as i32,
+                    neighbor_x as i32,
+                    neighbor_y as i32,
+                ));
+                frontier.push(FrontierItem {
+                    cost: priority,
+                    tie_breaker: 0,
+                    position: next_state,
+                });
+            }
+        }
+    }
+    let mut path = Vec::new();
+    if let Some(mut state) = found {
+        while came_from[state] != NO_PARENT {
+            let heading = HEADINGS[state / straight_states % 8];
+            path.push((to_tile(state), heading));
+            state = came_from[state];
+        }
+        path.reverse();
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_turns(path: &[(u32, Heading)]) -> usize {
+        path.windows(2)
+            .filter(|steps| steps[0].1 != steps[1].1)
+            .count()
+    }
+
+    #[test]
+    fn it_gets_headings_and_turns() {
+        assert_eq!(Heading::from_step(4, 1, 3), Heading::North);
+        assert_eq!(Heading::from_step(4, 8, 3), Heading::SouthEast);
+        assert_eq!(Heading::from_step(4, 3, 3), Heading::West);
+        assert_eq!(Heading::North.turns_to(Heading::North), 0);
+        assert_eq!(Heading::North.turns_to(Heading::NorthWest), 1);
+        assert_eq!(Heading::East.turns_to(Heading::West), 4);
+        assert_eq!(Heading::NorthWest.turns_to(Heading::East), 3);
+    }
+
+    #[test]
+    fn it_returns_headings_with_the_path() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        let path = find_path_with_headings(5, 9, &grid, 5, true, None, &TurnRules::default());
+        assert_eq!(
+            path,
+            vec![
+                (6, Heading::East),
+                (7, Heading::East),
+                (8, Heading::East),
+                (9, Heading::East),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_prefers_fewer_turns() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        let rules = TurnRules {
+            cost_per_turn: 5,
+            ..TurnRules::default()
+        };
+        let path = find_path_with_headings(0, 24, &grid, 5, true, None, &rules);
+        assert_eq!(path.len(), 8);
+        assert_eq!(count_turns(&path), 1);
+        assert_eq!(path.last().map(|step| step.0), Some(24));
+    }
+
+    #[test]
+    fn it_cannot_turn_sharper_than_allowed() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let rules = TurnRules {
+            max_turn: 1,
+            ..TurnRules::default()
+        };
+        let path = find_path_with_headings(24, 38, &grid, 7, false, Some(Heading::North), &rules);
+        assert_eq!(path.last().map(|step| step.0), Some(38));
+        assert!(Heading::North.turns_to(path[0].1) <= 1);
+        assert!(path
+            .windows(2)
+            .all(|steps| steps[0].1.turns_to(steps[1].1) <= 1));
+        assert!(path.len() > 2);
+    }
+
+    #[test]
+    fn it_goes_straight_between_turns() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        let rules = TurnRules {
+            min_straight_steps: 2,
+            ..TurnRules::default()
+        };
+        let path = find_path_with_headings(0, 24, &grid, 5, true, None, &rules);
+        assert_eq!(path.last().map(|step| step.0), Some(24));
+        let mut straight = u8::MAX;
+        for steps in path.windows(2) {
+            match steps[0].1 == steps[1].1 {
+                true => straight = straight.saturating_add(1),
+                _ => {
+                    assert!(straight >= 2);
+                    straight = 0;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_returns_the_end_when_already_there() {
+        let grid = vec![1; 9];
+        let rules = TurnRules::default();
+        assert_eq!(
+            find_path_with_headings(4, 4, &grid, 3, false, Some(Heading::East), &rules),
+            vec![(4, Heading::East)]
+        );
+        assert_eq!(
+            find_path_with_headings(4, 4, &grid, 3, false, None, &rules),
+            vec![(4, Heading::North)]
+        );
+        // Unlike a start with no way out.
+        let walled = vec![1, 0, 0, 0, 0, 0, 0, 0, 1];
+        assert!(find_path_with_headings(0, 8, &walled, 3, false, None, &rules).is_empty());
+    }
+
+    #[test]
+    fn it_skips_steps_that_overflow() {
+        let rules = TurnRules::default();
+        let grid = vec![1, u32::MAX, 1];
+        assert!(find_path_with_headings(0, 2, &grid, 3, true, None, &rules).is_empty());
+        // Every path from one corner to the other turns once.
+        let rules = TurnRules {
+            cost_per_turn: u32::MAX / 2,
+            ..TurnRules::default()
+        };
+        let grid = vec![1; 4];
+        assert!(find_path_with_headings(0, 3, &grid, 2, true, None, &rules).is_empty());
+        let rules = TurnRules {
+            cost_per_turn: u32::MAX / 4,
+            ..TurnRules::default()
+        };
+        assert_eq!(
+            find_path_with_headings(0, 3, &grid, 2, true, None, &rules).len(),
+            2
+        );
+    }
+}
@@ -1,35 +1,69 @@
 #![feature(test)]
 extern crate test;
 
+mod any_angle;
+mod anytime;
+pub mod ascii;
+mod clearance;
+mod cost;
+pub mod exits;
+mod heading;
+mod line_of_sight;
+pub mod moving_ai;
+mod observer;
+mod options;
+mod smoothing;
+mod stats;
+mod terrain;
+mod trace;
+mod walkability;
+
+pub use any_angle::{find_path_lazy_theta_star, find_path_theta_star, path_to_polyline};
+pub use anytime::{find_path_anytime, AnytimePath, AnytimeSearch};
+pub use clearance::{create_clearance_map, find_path_with_clearance};
+pub use cost::{Cost, Fixed, OrderedFloat, OverflowPolicy};
+pub use exits::{find_path_with_exits, get_exit_direction};
+pub use heading::{find_path_with_headings, Heading, TurnRules};
+pub use line_of_sight::{field_of_view, has_line_of_sight, raycast};
+pub use observer::{find_path_with_observer, SearchObserver};
+pub use options::{SearchOptions, TieBreaking};
+pub use smoothing::{remove_collinear_points, smooth_path};
+pub use stats::{measure, SearchContext, SearchStats, StatsObserver};
+pub use terrain::{find_path_for_agent, AgentProfile};
+pub use trace::{find_path_with_trace, SearchTrace, TraceImage};
+pub use walkability::{find_path_with_walkability, WalkabilityGrid};
+
 use smallvec::{smallvec, SmallVec};
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct FrontierItem {
-    pub position: u32,
-    pub cost: u32,
+struct FrontierItem<C = u32, P = u32> {
+    pub position: P,
+    pub cost: C,
+    pub tie_breaker: u64,
 }
 
-impl Ord for FrontierItem {
+impl<C: Ord, P: Ord> Ord for FrontierItem<C, P> {
     fn cmp(&self, other: &Self) -> Ordering {
         other
             .cost
             .cmp(&self.cost)
+            .then_with(|| other.tie_breaker.cmp(&self.tie_breaker))
             .then_with(|| self.position.cmp(&other.position))
     }
 }
 
-impl PartialOrd for FrontierItem {
+impl<C: Ord, P: Ord> PartialOrd for FrontierItem<C, P> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
 #[inline(always)]
-fn get_neighbor_coords(current: u32, grid: &Vec<u32>, width: u32) -> SmallVec<[u32; 8]> {
+fn get_neighbor_coords(current: u32, grid: &[u32], width: u32) -> SmallVec<[u32; 8]> {
     let is_top = current < width;
-    let is_bottom = current >= grid.len() as u32 - width;
+    let is_bottom = current as usize >= grid.len() - width as usize;
     let x = current % width;
     let is_left = x == 0;
     let is_right = x == width - 1;
@@ -68,9 +102,9 @@ fn get_neighbor_coords(current: u32, grid: &Vec<u32>, width: u32) -> SmallVec<[u
 }
 
 #[inline(always)]
-fn get_cardinal_neighbor_coords(current: u32, grid: &Vec<u32>, width: u32) -> SmallVec<[u32; 8]> {
+fn get_cardinal_neighbor_coords(current: u32, grid: &[u32], width: u32) -> SmallVec<[u32; 8]> {
     let is_top = current < width;
-    let is_bottom = current >= grid.len() as u32 - width;
+    let is_bottom = current as usize >= grid.len() - width as usize;
     let x = current % width;
     let is_left = x == 0;
     let is_right = x == width - 1;
@@ -101,62 +135,143 @@ fn manhattan(x1: i32, y1: i32, x2: i32, y2: i32) -> u32 {
     ((x1 - x2).abs() + (y1 - y2).abs()) as u32
 }
 
-pub fn find_path(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn search<C: Cost, F: Fn(u32, u32) -> Option<C>, O: SearchObserver<C>>(
     start: u32,
     end: u32,
-    grid: &Vec<u32>,
+    grid: &[u32],
     width: u32,
     cardinal_directions: bool,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+    observer: O,
 ) -> Vec<u32> {
+    let neighbor_coord_getter = match cardinal_directions {
+        true => get_cardinal_neighbor_coords,
+        _ => get_neighbor_coords,
+    };
+    search_with_neighbors(
+        start,
+        end,
+        grid.len(),
+        width,
+        |idx| neighbor_coord_getter(idx, grid, width),
+        get_cost_for_edge,
+        options,
+        observer,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn search_with_neighbors<
+    C: Cost,
+    F: Fn(u32, u32) -> Option<C>,
+    N: Fn(u32) -> SmallVec<[u32; 8]>,
+    O: SearchObserver<C>,
+>(
+    start: u32,
+    end: u32,
+    tile_count: usize,
+    width: u32,
+    get_neighbors: N,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+    mut observer: O,
+) -> Vec<u32> {
+    // Sizes are worked out in usize, and grids whose tiles can't all be
+    // indexed with u32 are turned away before any index arithmetic can wrap.
+    assert!(
+        tile_count <= u32::MAX as usize,
+        "a grid of {} tiles is too big for u32 indices, the cached crates can use u64",
+        tile_count
+    );
     let end_x = end % width;
     let end_y = end / width;
-    let mut frontier = BinaryHeap::with_capacity(grid.len());
-    let mut cost_so_far = vec![0; grid.len()];
-    let mut came_from = vec![start; grid.len()];
-    cost_so_far[start as usize] = 1;
+    let mut frontier = BinaryHeap::with_capacity(tile_count);
+    let mut cost_so_far: Vec<Option<C>> = vec![None; tile_count];
+    let mut came_from = vec![start; tile_count];
+    let mut closed = vec![false; tile_count];
+    cost_so_far[start as usize] = Some(C::ZERO);
+    observer.on_push(start, C::ZERO);
     frontier.push(FrontierItem {
-        cost: 0,
+        cost: C::ZERO,
+        tie_breaker: 0,
         position: start,
     });
-    let neighbor_coord_getter = match cardinal_directions {
-        true => get_cardinal_neighbor_coords,
-        _ => get_neighbor_coords,
-    };
     let mut found = false;
     while !frontier.is_empty() {
         let current_position = frontier.pop().unwrap().position;
+        // Tiles improved after being queued leave stale entries behind.
+        if closed[current_position as usize] {
+            observer.on_skip(current_position);
+            continue;
+        }
+        closed[current_position as usize] = true;
         if current_position == end {
             found = true;
             break;
         }
+        let current_cost =
+            cost_so_far[current_position as usize].expect("queued tiles have a cost");
+        observer.on_expand(current_position, current_cost);
         let current_x = current_position % width;
         let current_y = current_position / width;
-        let neighbor_coords = neighbor_coord_getter(current_position, grid, width);
+        let neighbor_coords = get_neighbors(current_position);
         for idx in 0..neighbor_coords.len() {
             let neighbor = neighbor_coords[idx];
-            let neighbor_cost = grid[neighbor as usize];
+            if closed[neighbor as usize] {
+                continue;
+            }
+            let neighbor_cost = match get_cost_for_edge(current_position, neighbor) {
+                Some(cost) => cost,
+                None => continue,
+            };
             let neighbor_x = neighbor % width;
             let neighbor_y = neighbor / width;
-            let cost = cost_so_far[current_position as usize]
-                + neighbor_cost
-                + manhattan(
-                    current_x as i32,
-                    current_y as i32,
+            let distance = C::from_tiles(manhattan(
+                current_x as i32,
+                current_y as i32,
+                neighbor_x as i32,
+                neighbor_y as i32,
+            ) as f64);
+            let cost = match options
+                .overflow
+                .add(current_cost, neighbor_cost)
+                .and_then(|cost| options.overflow.add(cost, distance))
+            {
+                Some(cost) => cost,
+                None => continue,
+            };
+            let is_cheaper = match cost_so_far[neighbor as usize] {
+                Some(neighbor_cost_so_far) if cost < neighbor_cost_so_far => {
+                    observer.on_relax(neighbor, neighbor_cost_so_far, cost);
+                    true
+                }
+                Some(_) => false,
+                None => true,
+            };
+            if is_cheaper {
+                cost_so_far[neighbor as usize] = Some(cost);
+                observer.on_push(neighbor, cost);
+                let heuristic = manhattan(
+                    end_x as i32,
+                    end_y as i32,
                     neighbor_x as i32,
                     neighbor_y as i32,
                 );
-            let neighbor_cost_so_far = cost_so_far[neighbor as usize];
-            if neighbor_cost_so_far == 0 || cost < neighbor_cost_so_far {
-                cost_so_far[neighbor as usize] = cost;
-                let priority = cost
-                    + manhattan(
-                        end_x as i32,
-                        end_y as i32,
-                        neighbor_x as i32,
-                        neighbor_y as i32,
-                    );
+                let priority = cost.saturating_add(C::from_tiles(
+                    heuristic as f64 * options.heuristic_weight as f64,
+                ));
                 frontier.push(FrontierItem {
                     cost: priority,
+                    tie_breaker: options.tie_breaking.rank(
+                        cost.order_key(),
+                        heuristic,
+                        neighbor,
+                        start,
+                        end,
+                        width,
+                    ),
                     position: neighbor,
                 });
                 came_from[neighbor as usize] = current_position;
@@ -174,10 +289,121 @@ pub fn find_path(
             }
         }
         path.reverse();
+        observer.on_path_found(&path);
     }
     path
 }
 
+/// Like `find_path`, but the cost of stepping from one tile to a neighbor is
+/// `get_cost_for_edge(from, to)` rather than the neighbor's value in `grid`.
+/// Returning `None` forbids the step. `grid` still decides which tiles are
+/// walls, and the distance moved is added on top of the returned cost.
+/// Returning `Some(0)` makes a tile free to enter, such as a road, which
+/// `grid` can't express since 0 there is a wall.
+pub fn find_path_with_edge_cost<C: Cost, F: Fn(u32, u32) -> Option<C>>(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    get_cost_for_edge: F,
+) -> Vec<u32> {
+    search(
+        start,
+        end,
+        grid,
+        width,
+        cardinal_directions,
+        get_cost_for_edge,
+        &SearchOptions::default(),
+        (),
+    )
+}
+
+/// Like `find_path_with_edge_cost`, with the search tuned by `options`.
+pub fn find_path_with_edge_cost_and_options<C: Cost, F: Fn(u32, u32) -> Option<C>>(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+) -> Vec<u32> {
+    search(
+        start,
+        end,
+        grid,
+        width,
+        cardinal_directions,
+        get_cost_for_edge,
+        options,
+        (),
+    )
+}
+
+/// Tiles are indexed with u32, so searches panic on grids of more than
+/// `u32::MAX` tiles. The cached crates can index them with u64 instead.
+pub fn find_path(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+) -> Vec<u32> {
+    find_path_with_edge_cost(start, end, grid, width, cardinal_directions, |_, idx| {
+        Some(grid[idx as usize])
+    })
+}
+
+/// Weighted A*: like `find_path`, but the heuristic is multiplied by
+/// `heuristic_weight`. Weights above 1 expand fewer tiles in exchange for
+/// paths that may cost up to `heuristic_weight` times the cheapest one.
+pub fn find_path_weighted(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    heuristic_weight: f32,
+) -> Vec<u32> {
+    search(
+        start,
+        end,
+        grid,
+        width,
+        cardinal_directions,
+        |_, idx| Some(grid[idx as usize]),
+        &SearchOptions {
+            heuristic_weight,
+            ..SearchOptions::default()
+        },
+        (),
+    )
+}
+
+/// Like `find_path`, with the heuristic weight and tie-breaking set by
+/// `options`.
+pub fn find_path_with_options(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    options: &SearchOptions,
+) -> Vec<u32> {
+    search(
+        start,
+        end,
+        grid,
+        width,
+        cardinal_directions,
+        |_, idx| Some(grid[idx as usize]),
+        options,
+        (),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,6 +512,134 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_uses_edge_costs() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let path = find_path_with_edge_cost(0, 2, &grid, 3, false, |_, to| Some(grid[to as usize]));
+        assert_eq!(path, vec![1, 2]);
+        let path = find_path_with_edge_cost(0, 2, &grid, 3, false, |_, to| match to {
+            1 => Some(10),
+            _ => Some(grid[to as usize]),
+        });
+        assert_eq!(path, vec![4, 2]);
+        let path = find_path_with_edge_cost(0, 2, &grid, 3, false, |from, to| match (from, to) {
+            (1, 2) => None,
+            _ => Some(grid[to as usize]),
+        });
+        assert_eq!(path, vec![4, 2]);
+    }
+
+    #[test]
+    fn it_paths_over_zero_cost_tiles() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        // The bottom row is a road, which is free to enter apart from the
+        // distance moved.
+        #[rustfmt::skip]
+        let costs: Vec<u32> = vec![
+            1, 9, 1,
+            0, 0, 0,
+        ];
+        let path =
+            find_path_with_edge_cost(0, 2, &grid, 3, false, |_, to| Some(costs[to as usize]));
+        assert!(!path.contains(&1));
+        assert_eq!(path.last(), Some(&2));
+        let path = find_path_with_edge_cost(0, 2, &grid, 3, false, |_, _| Some(0u32));
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[test]
+    fn it_weights_the_heuristic() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let path = find_path_weighted(0, 48, &grid, 7, false, 1.0);
+        assert_eq!(path, find_path(0, 48, &grid, 7, false));
+        let path = find_path_weighted(0, 48, &grid, 7, false, 3.0);
+        assert_eq!(path.last(), Some(&48));
+    }
+
+    #[test]
+    fn it_accumulates_other_cost_types() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let path = find_path_with_edge_cost(0, 2, &grid, 3, false, |_, to| {
+            Some(Fixed::from_f64(if to == 1 { 2.5 } else { 0.25 }))
+        });
+        assert_eq!(path, vec![4, 2]);
+        let path = find_path_with_edge_cost(0, 2, &grid, 3, false, |_, to| {
+            Some(OrderedFloat(if to == 1 { 2.0 } else { 0.25 }))
+        });
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[test]
+    fn it_handles_cost_overflow() {
+        let grid = vec![1, 1, 1];
+        let get_cost_for_edge = |_, to| Some(if to == 1 { u16::MAX } else { 1 });
+        let saturating = SearchOptions {
+            overflow: OverflowPolicy::Saturating,
+            ..SearchOptions::default()
+        };
+        let path = find_path_with_edge_cost_and_options(
+            0,
+            2,
+            &grid,
+            3,
+            false,
+            get_cost_for_edge,
+            &SearchOptions::default(),
+        );
+        assert_eq!(path, vec![]);
+        let path = find_path_with_edge_cost_and_options(
+            0,
+            2,
+            &grid,
+            3,
+            false,
+            get_cost_for_edge,
+            &saturating,
+        );
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[bench]
+    fn bench_it_crosses_rough_ground(b: &mut Bencher) {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 9, 1,
+            9, 9, 9, 9, 9, 9, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 9, 9, 9, 9, 9, 9,
+            1, 1, 1, 1, 1, 1, 1,
+            9, 1, 1, 1, 1, 1, 1,
+        ];
+        let (_, stats) =
+            measure(|observer| find_path_with_observer(0, 48, &grid, 7, false, observer));
+        assert_eq!((stats.expansions, stats.stale_entries), (41, 10));
+        b.iter(|| find_path(0, 48, &grid, 7, false));
+    }
+
     #[bench]
     fn bench_it_avoids_walls(b: &mut Bencher) {
         #[rustfmt::skip]
@@ -300,6 +654,23 @@ mod tests {
         ];
         b.iter(|| find_path(0, 48, &grid, 7, false));
     }
+
+    #[bench]
+    fn bench_it_avoids_walls_with_walkability(b: &mut Bencher) {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let walkability = WalkabilityGrid::new(&grid, 7);
+        b.iter(|| find_path_with_walkability(0, 48, &grid, 7, false, &walkability));
+    }
+
     #[bench]
     fn bench_it_avoids_walls_cardinal(b: &mut Bencher) {
         #[rustfmt::skip]
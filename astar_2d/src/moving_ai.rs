@@ -0,0 +1,483 @@
+//! Loading the `.map` and `.scen` files of the Moving AI pathfinding
+//! benchmarks, and checking the paths found for their scenarios against the
+//! optimal lengths the scenario files list.
+//!
+//! The benchmarks move in 8 directions with diagonal steps costing √2 and
+//! never cut corners, so scenarios are run with `find_octile_path` rather
+//! than `find_path`.
+
+use crate::{search, OrderedFloat, SearchObserver, SearchOptions};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// How far a path's length may be from a scenario's optimal length and still
+/// count as optimal, since scenario files round lengths to 8 decimal places.
+pub const LENGTH_TOLERANCE: f64 = 1e-4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    MissingHeader(&'static str),
+    InvalidHeader(String),
+    WrongRowCount {
+        expected: u32,
+        found: u32,
+    },
+    WrongRowWidth {
+        row: u32,
+        expected: u32,
+        found: u32,
+    },
+    UnknownTerrain {
+        row: u32,
+        column: u32,
+        terrain: char,
+    },
+    InvalidScenario {
+        line: u32,
+    },
+    /// The map has more tiles than a `u32` index can reach.
+    TooManyTiles {
+        width: u32,
+        height: u32,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::MissingHeader(field) => write!(f, "missing `{}` header", field),
+            ParseError::InvalidHeader(line) => write!(f, "invalid header line `{}`", line),
+            ParseError::WrongRowCount { expected, found } => {
+                write!(f, "expected {} rows but found {}", expected, found)
+            }
+            ParseError::WrongRowWidth {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} is {} tiles wide rather than {}",
+                row, found, expected
+            ),
+            ParseError::UnknownTerrain {
+                row,
+                column,
+                terrain,
+            } => write!(
+                f,
+                "unknown terrain `{}` at row {} column {}",
+                terrain, row, column
+            ),
+            ParseError::InvalidScenario { line } => write!(f, "invalid scenario on line {}", line),
+            ParseError::TooManyTiles { width, height } => {
+                write!(f, "a {}x{} map has too many tiles", width, height)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// A map in the grid format `find_path` expects: 1 for open ground and 0 for
+/// walls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Map {
+    pub width: u32,
+    pub height: u32,
+    pub grid: Vec<u32>,
+}
+
+impl FromStr for Map {
+    type Err = ParseError;
+
+    /// Parses a `.map` file. Ground (`.`, `G`) and swamp (`S`) are open, while
+    /// out of bounds (`@`, `O`), trees (`T`) and water (`W`) are walls. Water
+    /// can be crossed from other water in the benchmarks, but no scenario
+    /// relies on it.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines();
+        let mut width = None;
+        let mut height = None;
+        for line in lines.by_ref() {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("map"), None) => break,
+                (Some("type"), Some(_)) => {}
+                (Some("height"), Some(value)) => {
+                    height = Some(value.parse().map_err(|_| invalid_header(line))?)
+                }
+                (Some("width"), Some(value)) => {
+                    width = Some(value.parse().map_err(|_| invalid_header(line))?)
+                }
+                (None, _) => {}
+                _ => return Err(invalid_header(line)),
+            }
+        }
+        let width: u32 = width.ok_or(ParseError::MissingHeader("width"))?;
+        let height: u32 = height.ok_or(ParseError::MissingHeader("height"))?;
+        let tile_count = width
+            .checked_mul(height)
+            .ok_or(ParseError::TooManyTiles { width, height })?;
+        // Every tile takes at least a byte, so a header can't ask for more
+        // room than the text has.
+        let mut grid = Vec::with_capacity((tile_count as usize).min(text.len()));
+        let mut row_count = 0;
+        for (row, line) in lines.filter(|line| !line.is_empty()).enumerate() {
+            let row = row as u32;
+            let tiles = line.trim_end();
+            let found = tiles.chars().count() as u32;
+            if found != width {
+                return Err(ParseError::WrongRowWidth {
+                    row,
+                    expected: width,
+                    found,
+                });
+            }
+            for (column, terrain) in tiles.chars().enumerate() {
+                grid.push(match terrain {
+                    '.' | 'G' | 'S' => 1,
+                    '@' | 'O' | 'T' | 'W' => 0,
+                    _ => {
+                        return Err(ParseError::UnknownTerrain {
+                            row,
+                            column: column as u32,
+                            terrain,
+                        })
+                    }
+                });
+            }
+            row_count += 1;
+        }
+        if row_count != height {
+            return Err(ParseError::WrongRowCount {
+                expected: height,
+                found: row_count,
+            });
+        }
+        Ok(Map {
+            width,
+            height,
+            grid,
+        })
+    }
+}
+
+fn invalid_header(line: &str) -> ParseError {
+    ParseError::InvalidHeader(line.to_string())
+}
+
+/// One line of a `.scen` file, with coordinates already turned into tile
+/// indexes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scenario {
+    pub bucket: u32,
+    pub map_name: String,
+    pub map_width: u32,
+    pub map_height: u32,
+    pub start: u32,
+    pub end: u32,
+    pub optimal_length: f64,
+}
+
+/// Parses a `.scen` file, skipping its `version` line.
+pub fn parse_scenarios(text: &str) -> Result<Vec<Scenario>, ParseError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with("version"))
+        .map(|(line_number, line)| {
+            parse_scenario(line).ok_or(ParseError::InvalidScenario {
+                line: line_number as u32 + 1,
+            })
+        })
+        .collect()
+}
+
+fn parse_scenario(line: &str) -> Option<Scenario> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 9 {
+        return None;
+    }
+    let number = |idx: usize| fields[idx].parse::<u32>().ok();
+    let map_width = number(2)?;
+    let map_height = number(3)?;
+    let (start_x, start_y, end_x, end_y) = (number(4)?, number(5)?, number(6)?, number(7)?);
+    if start_x.max(end_x) >= map_width || start_y.max(end_y) >= map_height {
+        return None;
+    }
+    Some(Scenario {
+        bucket: number(0)?,
+        map_name: fields[1].to_string(),
+        map_width,
+        map_height,
+        start: start_y * map_width + start_x,
+        end: end_y * map_width + end_x,
+        optimal_length: fields[8].parse().ok()?,
+    })
+}
+
+/// Finds the shortest path from `start` to `end` by the benchmarks' rules:
+/// diagonal steps cost √2, every tile that isn't a wall costs the same and
+/// diagonals can't pass between two walls or along the side of one.
+pub fn find_octile_path(start: u32, end: u32, grid: &[u32], width: u32) -> Vec<u32> {
+    find_octile_path_with_observer(start, end, grid, width, ())
+}
+
+/// Like `find_octile_path`, telling `observer` about each step of the search.
+pub fn find_octile_path_with_observer<O: SearchObserver<OrderedFloat>>(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    observer: O,
+) -> Vec<u32> {
+    // The search adds the manhattan distance of every step on top of the edge
+    // cost, 1 for straight steps and 2 for diagonal ones. Edge costs of 1 and
+    // 2√2 - 2 make each step cost twice its length, so paths are ranked by
+    // length and the manhattan heuristic is still admissible.
+    let diagonal_cost = 2.0 * std::f64::consts::SQRT_2 - 2.0;
+    search(
+        start,
+        end,
+        grid,
+        width,
+        false,
+        |from, to| {
+            let (from_x, from_y) = (from % width, from / width);
+            let (to_x, to_y) = (to % width, to / width);
+            if from_x == to_x || from_y == to_y {
+                return Some(OrderedFloat(1.0));
+            }
+            let is_open = |x: u32, y: u32| grid[(y * width + x) as usize] > 0;
+            (is_open(to_x, from_y) && is_open(from_x, to_y)).then_some(OrderedFloat(diagonal_cost))
+        },
+        &SearchOptions::default(),
+        observer,
+    )
+}
+
+/// The length of a path returned by one of the searches, counting diagonal
+/// steps as √2. A search from the end returns `[end]`, which has no length.
+pub fn path_length(start: u32, path: &[u32], width: u32) -> f64 {
+    let mut from = start;
+    let mut length = 0.0;
+    for &to in path {
+        if to == from {
+            continue;
+        }
+        let diagonal = from % width != to % width && from / width != to / width;
+        length += match diagonal {
+            true => std::f64::consts::SQRT_2,
+            _ => 1.0,
+        };
+        from = to;
+    }
+    length
+}
+
+/// A scenario written for a map of another size than the one it was run on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapSizeMismatch {
+    /// The position of the scenario in the list given to `run_scenarios`.
+    pub scenario: usize,
+    pub expected: (u32, u32),
+    pub found: (u32, u32),
+}
+
+impl fmt::Display for MapSizeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "scenario {} is for a {}x{} map but the map is {}x{}",
+            self.scenario, self.expected.0, self.expected.1, self.found.0, self.found.1
+        )
+    }
+}
+
+impl Error for MapSizeMismatch {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioResult {
+    pub path: Vec<u32>,
+    pub length: f64,
+    pub optimal_length: f64,
+}
+
+impl ScenarioResult {
+    pub fn is_optimal(&self) -> bool {
+        !self.path.is_empty() && (self.length - self.optimal_length).abs() <= LENGTH_TOLERANCE
+    }
+}
+
+/// Runs every scenario on `map` and measures the paths found. Nothing is run
+/// if any scenario was written for a map of another size.
+pub fn run_scenarios(
+    map: &Map,
+    scenarios: &[Scenario],
+) -> Result<Vec<ScenarioResult>, MapSizeMismatch> {
+    for (idx, scenario) in scenarios.iter().enumerate() {
+        if (scenario.map_width, scenario.map_height) != (map.width, map.height) {
+            return Err(MapSizeMismatch {
+                scenario: idx,
+                expected: (scenario.map_width, scenario.map_height),
+                found: (map.width, map.height),
+            });
+        }
+    }
+    Ok(scenarios
+        .iter()
+        .map(|scenario| {
+            let path = find_octile_path(scenario.start, scenario.end, &map.grid, map.width);
+            ScenarioResult {
+                length: path_length(scenario.start, &path, map.width),
+                path,
+                optimal_length: scenario.optimal_length,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test::Bencher;
+
+    const ROOMS_MAP: &str = include_str!("../maps/rooms.map");
+    const ROOMS_SCENARIOS: &str = include_str!("../maps/rooms.map.scen");
+    const MAZE_MAP: &str = include_str!("../maps/maze.map");
+    const MAZE_SCENARIOS: &str = include_str!("../maps/maze.map.scen");
+
+    #[test]
+    fn it_parses_maps() {
+        let map: Map = "type octile\nheight 2\nwidth 3\nmap\n.@T\nGS.\n"
+            .parse()
+            .unwrap();
+        assert_eq!(map.width, 3);
+        assert_eq!(map.height, 2);
+        assert_eq!(map.grid, vec![1, 0, 0, 1, 1, 1]);
+        assert_eq!(
+            "type octile\nheight 2\nwidth 3\nmap\n...\n".parse::<Map>(),
+            Err(ParseError::WrongRowCount {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            "type octile\nheight 1\nwidth 3\nmap\n.?.\n".parse::<Map>(),
+            Err(ParseError::UnknownTerrain {
+                row: 0,
+                column: 1,
+                terrain: '?'
+            })
+        );
+        assert_eq!(
+            "type octile\nwidth 3\nmap\n...\n".parse::<Map>(),
+            Err(ParseError::MissingHeader("height"))
+        );
+        assert_eq!(
+            "type octile\nheight 65536\nwidth 65536\nmap\n".parse::<Map>(),
+            Err(ParseError::TooManyTiles {
+                width: 65536,
+                height: 65536
+            })
+        );
+        // A huge map that fits is only refused once its rows run out.
+        assert_eq!(
+            "type octile\nheight 65535\nwidth 65535\nmap\n".parse::<Map>(),
+            Err(ParseError::WrongRowCount {
+                expected: 65535,
+                found: 0
+            })
+        );
+    }
+
+    #[test]
+    fn it_parses_scenarios() {
+        let scenarios =
+            parse_scenarios("version 1\n1\tarena.map\t4\t3\t0\t0\t3\t2\t3.82842712\n").unwrap();
+        assert_eq!(
+            scenarios,
+            vec![Scenario {
+                bucket: 1,
+                map_name: "arena.map".to_string(),
+                map_width: 4,
+                map_height: 3,
+                start: 0,
+                end: 11,
+                optimal_length: 3.82842712,
+            }]
+        );
+        assert_eq!(
+            parse_scenarios("version 1\n1\tarena.map\t4\t3\t0\t0\t4\t2\t3.8\n"),
+            Err(ParseError::InvalidScenario { line: 2 })
+        );
+    }
+
+    #[test]
+    fn it_does_not_cut_corners() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1,
+        ];
+        let path = find_octile_path(0, 8, &grid, 3);
+        assert!(path == vec![1, 2, 5, 8] || path == vec![3, 6, 7, 8]);
+        assert_eq!(path_length(0, &path, 3), 4.0);
+    }
+
+    #[test]
+    fn it_measures_a_search_from_the_end_as_empty() {
+        let map: Map = "type octile\nheight 2\nwidth 2\nmap\n..\n..\n"
+            .parse()
+            .unwrap();
+        let scenarios = parse_scenarios("1\tsmall.map\t2\t2\t1\t1\t1\t1\t0\n").unwrap();
+        let result = &run_scenarios(&map, &scenarios).unwrap()[0];
+        assert_eq!(result.path, vec![3]);
+        assert_eq!(result.length, 0.0);
+        assert!(result.is_optimal());
+    }
+
+    #[test]
+    fn it_finds_optimal_paths_for_bundled_scenarios() {
+        for (map, scenarios) in [(ROOMS_MAP, ROOMS_SCENARIOS), (MAZE_MAP, MAZE_SCENARIOS)] {
+            let map: Map = map.parse().unwrap();
+            let scenarios = parse_scenarios(scenarios).unwrap();
+            for (scenario, result) in scenarios
+                .iter()
+                .zip(run_scenarios(&map, &scenarios).unwrap())
+            {
+                assert!(result.is_optimal(), "{:?} gave {:?}", scenario, result);
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_scenarios_for_other_maps() {
+        let map: Map = "type octile\nheight 2\nwidth 2\nmap\n..\n..\n"
+            .parse()
+            .unwrap();
+        let scenarios = parse_scenarios("1\tsmall.map\t2\t2\t0\t0\t1\t1\t1.41421356\n1\tlarge.map\t4\t3\t0\t0\t1\t1\t1.41421356\n").unwrap();
+        assert_eq!(
+            run_scenarios(&map, &scenarios[..1]).unwrap()[0].path,
+            vec![3]
+        );
+        assert_eq!(
+            run_scenarios(&map, &scenarios),
+            Err(MapSizeMismatch {
+                scenario: 1,
+                expected: (4, 3),
+                found: (2, 2)
+            })
+        );
+    }
+
+    #[bench]
+    fn bench_it_runs_bundled_scenarios(b: &mut Bencher) {
+        let map: Map = ROOMS_MAP.parse().unwrap();
+        let scenarios = parse_scenarios(ROOMS_SCENARIOS).unwrap();
+        b.iter(|| run_scenarios(&map, &scenarios).unwrap());
+    }
+}
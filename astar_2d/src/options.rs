@@ -0,0 +1,152 @@
+use crate::OverflowPolicy;
+
+/// How to order tiles whose priorities are equal. Every strategy only uses
+/// integer arithmetic and falls back to the tile index, so the same inputs
+/// give the same path on every platform.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TieBreaking {
+    /// Prefer higher tile indices. This is the original ordering.
+    #[default]
+    Position,
+    /// Prefer the tile that is furthest from the start.
+    HigherCost,
+    /// Prefer the tile that is estimated to be closest to the end.
+    LowerHeuristic,
+    /// Prefer the tile closest to the straight line from the start to the end,
+    /// which avoids staircase paths on open ground.
+    Straightness,
+}
+
+impl TieBreaking {
+    // Among equal priorities the lowest rank is expanded first.
+    #[inline(always)]
+    pub(crate) fn rank(
+        self,
+        cost: u64,
+        heuristic: u32,
+        idx: u32,
+        start: u32,
+        end: u32,
+        width: u32,
+    ) -> u64 {
+        match self {
+            TieBreaking::Position => 0,
+            TieBreaking::HigherCost => u64::MAX - cost,
+            TieBreaking::LowerHeuristic => heuristic as u64,
+            TieBreaking::Straightness => {
+                let (end_x, end_y) = ((end % width) as i64, (end / width) as i64);
+                let line_x = (start % width) as i64 - end_x;
+                let line_y = (start / width) as i64 - end_y;
+                let offset_x = (idx % width) as i64 - end_x;
+                let offset_y = (idx / width) as i64 - end_y;
+                (line_x * offset_y - line_y * offset_x).unsigned_abs()
+            }
+        }
+    }
+}
+
+/// Settings for `find_path_with_options`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchOptions {
+    /// Multiplies the heuristic, see `find_path_weighted`.
+    pub heuristic_weight: f32,
+    pub tie_breaking: TieBreaking,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            heuristic_weight: 1.0,
+            tie_breaking: TieBreaking::default(),
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_path, find_path_with_options};
+
+    const STRATEGIES: [TieBreaking; 4] = [
+        TieBreaking::Position,
+        TieBreaking::HigherCost,
+        TieBreaking::LowerHeuristic,
+        TieBreaking::Straightness,
+    ];
+
+    #[test]
+    fn it_ranks_tiles_on_the_line_first() {
+        assert_eq!(TieBreaking::Straightness.rank(0, 0, 16, 0, 32, 8), 0);
+        assert_eq!(TieBreaking::Straightness.rank(0, 0, 9, 0, 32, 8), 4);
+        assert!(
+            TieBreaking::LowerHeuristic.rank(0, 1, 0, 0, 0, 8)
+                < TieBreaking::LowerHeuristic.rank(0, 2, 0, 0, 0, 8)
+        );
+        assert!(
+            TieBreaking::HigherCost.rank(2, 0, 0, 0, 0, 8)
+                < TieBreaking::HigherCost.rank(1, 0, 0, 0, 0, 8)
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_original_ordering_by_default() {
+        let grid = vec![1; 35];
+        for cardinal in [false, true] {
+            assert_eq!(
+                find_path_with_options(0, 34, &grid, 7, cardinal, &SearchOptions::default()),
+                find_path(0, 34, &grid, 7, cardinal)
+            );
+        }
+    }
+
+    #[test]
+    fn it_breaks_ties_towards_the_straight_line() {
+        let grid = vec![1; 35];
+        let options = SearchOptions {
+            tie_breaking: TieBreaking::Straightness,
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            find_path_with_options(0, 34, &grid, 7, true, &options),
+            vec![1, 8, 9, 16, 17, 18, 25, 26, 33, 34]
+        );
+        assert_eq!(
+            find_path_with_options(0, 34, &grid, 7, false, &options),
+            vec![8, 9, 17, 25, 26, 34]
+        );
+    }
+
+    #[test]
+    fn it_finds_equally_short_paths_with_every_strategy() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        for cardinal in [false, true] {
+            let shortest = find_path(0, 48, &grid, 7, cardinal).len();
+            for tie_breaking in STRATEGIES {
+                let options = SearchOptions {
+                    tie_breaking,
+                    ..SearchOptions::default()
+                };
+                let path = find_path_with_options(0, 48, &grid, 7, cardinal, &options);
+                assert_eq!(path.len(), shortest);
+                assert_eq!(path.last(), Some(&48));
+                assert_eq!(
+                    path,
+                    find_path_with_options(0, 48, &grid, 7, cardinal, &options)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::line_of_sight::{has_line_of_sight, walk_line};
+
+#[inline(always)]
+fn delta(from: u32, to: u32, width: u32) -> (i64, i64) {
+    (
+        (to % width) as i64 - (from % width) as i64,
+        (to / width) as i64 - (from / width) as i64,
+    )
+}
+
+#[inline(always)]
+fn is_collinear(previous: u32, current: u32, next: u32, width: u32) -> bool {
+    let (ax, ay) = delta(previous, current, width);
+    let (bx, by) = delta(current, next, width);
+    ax * by == ay * bx && ax * bx + ay * by > 0
+}
+
+/// Drops waypoints that sit on a straight line between their neighbors,
+/// leaving only the turning points and the end of `path`.
+pub fn remove_collinear_points(
+    start: u32,
+    path: &[u32],
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+) -> Vec<u32> {
+    let mut simplified = Vec::with_capacity(path.len());
+    let mut anchor = start;
+    for (idx, &current) in path.iter().enumerate() {
+        match path.get(idx + 1) {
+            Some(&next)
+                if is_collinear(anchor, current, next, width)
+                    && has_line_of_sight(anchor, next, grid, width, cardinal_directions) => {}
+            _ => {
+                simplified.push(current);
+                anchor = current;
+            }
+        }
+    }
+    simplified
+}
+
+/// String pulling: skips every waypoint that can be bypassed in a straight
+/// line. With `turning_points_only` the remaining waypoints are returned,
+/// otherwise the straight lines between them are walked so the result steps
+/// tile by tile like `find_path` output.
+pub fn smooth_path(
+    start: u32,
+    path: &[u32],
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    turning_points_only: bool,
+) -> Vec<u32> {
+    let mut waypoints = vec![];
+    let mut anchor = start;
+    let mut previous = start;
+    for &current in path {
+        if previous != anchor
+            && !has_line_of_sight(anchor, current, grid, width, cardinal_directions)
+        {
+            waypoints.push(previous);
+            anchor = previous;
+        }
+        previous = current;
+    }
+    if let Some(&end) = path.last() {
+        waypoints.push(end);
+    }
+    if turning_points_only {
+        return waypoints;
+    }
+    let mut tiles = Vec::with_capacity(path.len());
+    let mut from = start;
+    for to in waypoints {
+        walk_line(from, to, grid, width, cardinal_directions, |idx| {
+            tiles.push(idx)
+        });
+        from = to;
+    }
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    fn assert_steps_are_adjacent(start: u32, path: &[u32], width: u32, cardinal: bool) {
+        let mut from = start;
+        for &to in path {
+            let (dx, dy) = delta(from, to, width);
+            match cardinal {
+                true => assert_eq!(dx.abs() + dy.abs(), 1),
+                _ => assert!(dx.abs() <= 1 && dy.abs() <= 1 && from != to),
+            }
+            from = to;
+        }
+    }
+
+    #[test]
+    fn it_removes_collinear_points() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let path = find_path(0, 48, &grid, 7, false);
+        assert_eq!(path, vec![8, 15, 22, 29, 37, 45, 46, 47, 48]);
+        assert_eq!(
+            remove_collinear_points(0, &path, &grid, 7, false),
+            vec![8, 29, 45, 48]
+        );
+    }
+
+    #[test]
+    fn it_pulls_the_string_tight() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let path = find_path(0, 48, &grid, 7, false);
+        let waypoints = smooth_path(0, &path, &grid, 7, false, true);
+        assert_eq!(waypoints, vec![29, 47, 48]);
+        let mut from = 0;
+        for &to in &waypoints {
+            assert!(has_line_of_sight(from, to, &grid, 7, false));
+            from = to;
+        }
+    }
+
+    #[test]
+    fn it_keeps_smoothed_paths_traversable() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        for cardinal in [false, true] {
+            let path = find_path(0, 24, &grid, 7, cardinal);
+            let tiles = smooth_path(0, &path, &grid, 7, cardinal, false);
+            assert_eq!(tiles.last(), Some(&24));
+            assert!(tiles.len() <= path.len());
+            assert!(tiles.iter().all(|&idx| grid[idx as usize] > 0));
+            assert_steps_are_adjacent(0, &tiles, 7, cardinal);
+        }
+    }
+
+    #[test]
+    fn it_leaves_empty_paths_empty() {
+        let grid = vec![1, 1, 1, 1];
+        assert_eq!(remove_collinear_points(0, &[], &grid, 2, false), vec![]);
+        assert_eq!(smooth_path(0, &[], &grid, 2, false, false), vec![]);
+    }
+}
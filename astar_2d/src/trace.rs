@@ -0,0 +1,321 @@
+use crate::{get_cardinal_neighbor_coords, get_neighbor_coords, search_with_neighbors};
+use crate::{Cost, SearchObserver, SearchOptions};
+use std::fmt::Write;
+
+/// What a search did on its way to a path, for drawing with `to_svg` or
+/// `to_image`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchTrace<C = u32> {
+    /// Every tile whose neighbors were looked at, in order, with the cost of
+    /// reaching it.
+    pub expanded: Vec<(u32, C)>,
+    /// Tiles that were queued but never expanded when the search stopped.
+    pub frontier: Vec<u32>,
+}
+
+/// Like `find_path`, also returning a trace of the search.
+pub fn find_path_with_trace(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+) -> (Vec<u32>, SearchTrace) {
+    let neighbor_coord_getter = match cardinal_directions {
+        true => get_cardinal_neighbor_coords,
+        _ => get_neighbor_coords,
+    };
+    let mut trace = SearchTrace::default();
+    let path = search_with_neighbors(
+        start,
+        end,
+        grid.len(),
+        width,
+        |idx| neighbor_coord_getter(idx, grid, width),
+        |_, idx| Some(grid[idx as usize]),
+        &SearchOptions::default(),
+        &mut trace,
+    );
+    // Pushed tiles are kept once each, and only if they were never taken off
+    // the queue.
+    let mut seen = vec![false; grid.len()];
+    seen[end as usize] = true;
+    for &(idx, _) in &trace.expanded {
+        seen[idx as usize] = true;
+    }
+    trace
+        .frontier
+        .retain(|&idx| !std::mem::replace(&mut seen[idx as usize], true));
+    (path, trace)
+}
+
+impl<C> SearchObserver<C> for SearchTrace<C> {
+    fn on_push(&mut self, idx: u32, _cost: C) {
+        self.frontier.push(idx);
+    }
+
+    fn on_expand(&mut self, idx: u32, cost: C) {
+        self.expanded.push((idx, cost));
+    }
+}
+
+const WALL: [u8; 3] = [40, 40, 40];
+const FLOOR: [u8; 3] = [255, 255, 255];
+const CHEAPEST: [u8; 3] = [255, 236, 150];
+const MOST_EXPENSIVE: [u8; 3] = [225, 70, 35];
+const FRONTIER: [u8; 3] = [140, 200, 255];
+const PATH: [u8; 3] = [40, 90, 220];
+const START: [u8; 3] = [40, 170, 60];
+const END: [u8; 3] = [200, 30, 30];
+
+impl<C: Cost> SearchTrace<C> {
+    // The color of every tile. Expanded tiles are shaded from yellow to red by
+    // their cost, and heavier floor is drawn darker.
+    fn colors(&self, grid: &[u32], start: u32, path: &[u32]) -> Vec<[u8; 3]> {
+        let mut colors: Vec<[u8; 3]> = grid
+            .iter()
+            .map(|&cost| match cost {
+                0 => WALL,
+                _ => FLOOR.map(|channel| channel - (cost - 1).min(8) as u8 * 12),
+            })
+            .collect();
+        let keys = self.expanded.iter().map(|(_, cost)| cost.order_key());
+        let (cheapest, most_expensive) = (keys.clone().min(), keys.max());
+        if let (Some(cheapest), Some(most_expensive)) = (cheapest, most_expensive) {
+            let range = (most_expensive - cheapest).max(1) as f64;
+            for (idx, cost) in &self.expanded {
+                let shade = (cost.order_key() - cheapest) as f64 / range;
+                colors[*idx as usize] = blend(CHEAPEST, MOST_EXPENSIVE, shade);
+            }
+        }
+        for &idx in &self.frontier {
+            colors[idx as usize] = FRONTIER;
+        }
+        for &idx in path {
+            colors[idx as usize] = PATH;
+        }
+        if let Some(&end) = path.last() {
+            colors[end as usize] = END;
+        }
+        colors[start as usize] = START;
+        colors
+    }
+
+    /// Draws the grid with one square per tile. Hovering over an expanded
+    /// tile shows the cost of reaching it.
+    pub fn to_svg(&self, grid: &[u32], width: u32, start: u32, path: &[u32]) -> String {
+        let height = grid.len() as u32 / width;
+        let colors = self.colors(grid, start, path);
+        let mut costs = vec![None; grid.len()];
+        for (idx, cost) in &self.expanded {
+            costs[*idx as usize] = Some(cost);
+        }
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\" shape-rendering=\"crispEdges\">\n",
+            width,
+            height,
+            width * 16,
+            height * 16
+        );
+        for (idx, color) in colors.iter().enumerate() {
+            let (x, y) = (idx as u32 % width, idx as u32 / width);
+            let fill = format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]);
+            match costs[idx] {
+                Some(cost) => writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"{}\"><title>({}, {}) cost {:?}</title></rect>",
+                    x, y, fill, x, y, cost
+                ),
+                None => writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"{}\"/>",
+                    x, y, fill
+                ),
+            }
+            .unwrap();
+        }
+        svg += "</svg>\n";
+        svg
+    }
+
+    /// Draws the grid with each tile as a `tile_size` pixel square.
+    pub fn to_image(
+        &self,
+        grid: &[u32],
+        width: u32,
+        start: u32,
+        path: &[u32],
+        tile_size: u32,
+    ) -> TraceImage {
+        let height = grid.len() as u32 / width;
+        let colors = self.colors(grid, start, path);
+        let mut image = TraceImage {
+            width: width * tile_size,
+            height: height * tile_size,
+            pixels: vec![],
+        };
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let idx = y / tile_size * width + x / tile_size;
+                image.pixels.push(colors[idx as usize]);
+            }
+        }
+        image
+    }
+}
+
+fn blend(from: [u8; 3], to: [u8; 3], amount: f64) -> [u8; 3] {
+    [0, 1, 2].map(|channel| {
+        (from[channel] as f64 + (to[channel] as f64 - from[channel] as f64) * amount).round() as u8
+    })
+}
+
+/// An RGB image drawn from a `SearchTrace`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceImage {
+    pub width: u32,
+    pub height: u32,
+    /// Rows of pixels from the top down.
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl TraceImage {
+    /// Encodes the image as a binary PPM (P6).
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend(self.pixels.iter().flatten());
+        ppm
+    }
+
+    /// Encodes the image as a PNG. The pixels are stored without compression
+    /// to keep the encoder small, so files are about as large as a PPM.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut header = vec![];
+        header.extend(self.width.to_be_bytes());
+        header.extend(self.height.to_be_bytes());
+        // 8 bits per channel, RGB, and the standard compression, filter and
+        // interlace methods.
+        header.extend([8, 2, 0, 0, 0]);
+        let mut rows = vec![];
+        for row in self.pixels.chunks(self.width.max(1) as usize) {
+            // No filter.
+            rows.push(0);
+            rows.extend(row.iter().flatten());
+        }
+        let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        write_png_chunk(&mut png, b"IHDR", &header);
+        write_png_chunk(&mut png, b"IDAT", &zlib_store(&rows));
+        write_png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+// A zlib stream of uncompressed deflate blocks.
+fn zlib_store(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        stream.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        stream.push(blocks.peek().is_none() as u8);
+        stream.extend((block.len() as u16).to_le_bytes());
+        stream.extend((!(block.len() as u16)).to_le_bytes());
+        stream.extend(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    stream.extend((b << 16 | a).to_be_bytes());
+    stream
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => crc >> 1 ^ 0xedb8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    #[rustfmt::skip]
+    const GRID: [u32; 49] = [
+        1, 1, 1, 1, 1, 1, 1,
+        1, 1, 0, 1, 1, 0, 1,
+        1, 1, 0, 0, 1, 0, 1,
+        1, 1, 0, 1, 1, 0, 1,
+        1, 1, 0, 0, 0, 0, 1,
+        1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1,
+    ];
+
+    #[test]
+    fn it_traces_searches() {
+        let (path, trace) = find_path_with_trace(0, 48, &GRID, 7, false);
+        assert_eq!(path, find_path(0, 48, &GRID, 7, false));
+        assert_eq!(trace.expanded[0], (0, 0));
+        let mut seen = vec![false; GRID.len()];
+        for &(idx, _) in &trace.expanded {
+            assert!(!std::mem::replace(&mut seen[idx as usize], true));
+        }
+        for &idx in &trace.frontier {
+            assert!(!std::mem::replace(&mut seen[idx as usize], true));
+        }
+        assert!(!trace.frontier.is_empty());
+    }
+
+    #[test]
+    fn it_draws_traces() {
+        let (path, trace) = find_path_with_trace(0, 48, &GRID, 7, false);
+        let svg = trace.to_svg(&GRID, 7, 0, &path);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<rect").count(), 49);
+        assert_eq!(svg.matches("<title>").count(), trace.expanded.len());
+        let image = trace.to_image(&GRID, 7, 0, &path, 2);
+        assert_eq!((image.width, image.height), (14, 14));
+        assert_eq!(image.pixels[0], START);
+        assert_eq!(image.pixels[14 * 2 + 4], WALL);
+        assert_eq!(image.pixels[14 * 14 - 1], END);
+        let ppm = image.to_ppm();
+        assert!(ppm.starts_with(b"P6\n14 14\n255\n"));
+        assert_eq!(ppm.len(), 13 + 14 * 14 * 3);
+    }
+
+    #[test]
+    fn it_encodes_pngs() {
+        let image = TraceImage {
+            width: 1,
+            height: 1,
+            pixels: vec![[255, 0, 0]],
+        };
+        let png = image.to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        // The end chunk never changes, checksum included.
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+}
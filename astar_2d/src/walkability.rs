@@ -0,0 +1,280 @@
+use crate::{search_with_neighbors, SearchOptions};
+use smallvec::{smallvec, SmallVec};
+
+/// One bit per tile saying whether it can be entered, packed into words a row
+/// at a time. Neighbors are read three at a time from each row rather than
+/// tile by tile from the cost grid, and runs of open tiles can be measured a
+/// word at a time. Rows are padded to whole words with walls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WalkabilityGrid {
+    width: u32,
+    height: u32,
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+impl WalkabilityGrid {
+    /// Marks every tile with a cost above 0 as walkable.
+    pub fn new(grid: &[u32], width: u32) -> Self {
+        let height = grid.len() as u32 / width;
+        let words_per_row = (width as usize).div_ceil(64);
+        let mut walkability = Self {
+            width,
+            height,
+            words_per_row,
+            bits: vec![0; words_per_row * height as usize],
+        };
+        for (idx, &cost) in grid.iter().enumerate() {
+            walkability.set_walkable(idx as u32, cost > 0);
+        }
+        walkability
+    }
+
+    #[inline(always)]
+    fn locate(&self, idx: u32) -> (usize, u32) {
+        let row = (idx / self.width) as usize;
+        let x = idx % self.width;
+        (row * self.words_per_row + x as usize / 64, x % 64)
+    }
+
+    #[inline(always)]
+    pub fn is_walkable(&self, idx: u32) -> bool {
+        let (word, bit) = self.locate(idx);
+        self.bits[word] >> bit & 1 == 1
+    }
+
+    pub fn set_walkable(&mut self, idx: u32, walkable: bool) {
+        let (word, bit) = self.locate(idx);
+        match walkable {
+            true => self.bits[word] |= 1 << bit,
+            _ => self.bits[word] &= !(1 << bit),
+        }
+    }
+
+    /// Brings the given tiles back in sync with `grid` after it has changed.
+    pub fn update(&mut self, grid: &[u32], update_idxs: &[u32]) {
+        for &idx in update_idxs {
+            self.set_walkable(idx, grid[idx as usize] > 0);
+        }
+    }
+
+    // The 64 tiles of `row` starting at `x`, with tiles past the end of the
+    // row read as walls.
+    #[inline(always)]
+    fn read_row(&self, row: u32, x: u32) -> u64 {
+        let words = &self.bits[row as usize * self.words_per_row..][..self.words_per_row];
+        let (word, bit) = (x as usize / 64, x % 64);
+        let low = words.get(word).map_or(0, |word| word >> bit);
+        let high = match bit {
+            0 => 0,
+            _ => words.get(word + 1).map_or(0, |word| word << (64 - bit)),
+        };
+        low | high
+    }
+
+    // Tiles `x - 1`, `x` and `x + 1` of `row` in the lowest three bits.
+    #[inline(always)]
+    fn read_window(&self, row: u32, x: u32) -> u64 {
+        match x {
+            0 => self.read_row(row, 0) << 1 & 0b111,
+            _ => self.read_row(row, x - 1) & 0b111,
+        }
+    }
+
+    /// How many walkable tiles there are in a row east of `idx`, stopping at
+    /// the first wall or the edge of the map.
+    pub fn count_walkable_east(&self, idx: u32) -> u32 {
+        let (row, mut x) = (idx / self.width, idx % self.width + 1);
+        let mut count = 0;
+        while x < self.width {
+            let run = self.read_row(row, x).trailing_ones();
+            count += run;
+            if run < 64 {
+                break;
+            }
+            x += 64;
+        }
+        count.min(self.width - idx % self.width - 1)
+    }
+
+    /// How many walkable tiles there are in a row west of `idx`, stopping at
+    /// the first wall or the edge of the map.
+    pub fn count_walkable_west(&self, idx: u32) -> u32 {
+        let (row, x) = (idx / self.width, idx % self.width);
+        let mut count = 0;
+        let mut end = x;
+        while end > 0 {
+            let start = end.saturating_sub(64);
+            let length = end - start;
+            // The tiles from `start` up to `end`, nearest first in the highest
+            // bit.
+            let tiles = self.read_row(row, start) << (64 - length);
+            let run = tiles.leading_ones().min(length);
+            count += run;
+            if run < length {
+                break;
+            }
+            end = start;
+        }
+        count
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_neighbor_coords(
+        &self,
+        current: u32,
+        cardinal_directions: bool,
+    ) -> SmallVec<[u32; 8]> {
+        let width = self.width;
+        let (x, y) = (current % width, current / width);
+        let above = match y {
+            0 => 0,
+            _ => self.read_window(y - 1, x),
+        };
+        let middle = self.read_window(y, x);
+        let below = match y + 1 < self.height {
+            true => self.read_window(y + 1, x),
+            _ => 0,
+        };
+        let mut neighbors: SmallVec<[u32; 8]> = smallvec![];
+        if above & 0b010 != 0 {
+            neighbors.push(current - width);
+        }
+        if !cardinal_directions {
+            if above & 0b001 != 0 {
+                neighbors.push(current - width - 1);
+            }
+            if above & 0b100 != 0 {
+                neighbors.push(current - width + 1);
+            }
+        }
+        if middle & 0b001 != 0 {
+            neighbors.push(current - 1);
+        }
+        if middle & 0b100 != 0 {
+            neighbors.push(current + 1);
+        }
+        if below & 0b010 != 0 {
+            neighbors.push(current + width);
+        }
+        if !cardinal_directions {
+            if below & 0b001 != 0 {
+                neighbors.push(current + width - 1);
+            }
+            if below & 0b100 != 0 {
+                neighbors.push(current + width + 1);
+            }
+        }
+        neighbors
+    }
+}
+
+/// Like `find_path`, but walls come from `walkability` rather than from 0s in
+/// `grid`, which only supplies costs. This lets tiles with a cost of 0 be
+/// walked on for free.
+pub fn find_path_with_walkability(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    walkability: &WalkabilityGrid,
+) -> Vec<u32> {
+    search_with_neighbors(
+        start,
+        end,
+        grid.len(),
+        width,
+        |idx| walkability.get_neighbor_coords(idx, cardinal_directions),
+        |_, idx| Some(grid[idx as usize]),
+        &SearchOptions::default(),
+        (),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_path, get_cardinal_neighbor_coords, get_neighbor_coords};
+
+    #[test]
+    fn it_packs_walkability_into_bits() {
+        let grid: Vec<u32> = (0..200).map(|idx| (idx % 7 != 3) as u32).collect();
+        let mut walkability = WalkabilityGrid::new(&grid, 100);
+        for idx in 0..200 {
+            assert_eq!(walkability.is_walkable(idx), grid[idx as usize] > 0);
+        }
+        walkability.set_walkable(3, true);
+        assert!(walkability.is_walkable(3));
+        walkability.update(&grid, &[3]);
+        assert!(!walkability.is_walkable(3));
+    }
+
+    #[test]
+    fn it_gets_the_same_neighbors_as_the_grid() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let walkability = WalkabilityGrid::new(&grid, 7);
+        for idx in 0..grid.len() as u32 {
+            assert_eq!(
+                walkability.get_neighbor_coords(idx, false),
+                get_neighbor_coords(idx, &grid, 7)
+            );
+            assert_eq!(
+                walkability.get_neighbor_coords(idx, true),
+                get_cardinal_neighbor_coords(idx, &grid, 7)
+            );
+        }
+    }
+
+    #[test]
+    fn it_counts_walkable_runs_across_words() {
+        let mut grid = vec![1; 300];
+        grid[10] = 0;
+        grid[140] = 0;
+        let walkability = WalkabilityGrid::new(&grid, 150);
+        assert_eq!(walkability.count_walkable_east(0), 9);
+        assert_eq!(walkability.count_walkable_east(11), 128);
+        assert_eq!(walkability.count_walkable_east(141), 8);
+        assert_eq!(walkability.count_walkable_east(149), 0);
+        assert_eq!(walkability.count_walkable_east(150), 149);
+        assert_eq!(walkability.count_walkable_west(139), 128);
+        assert_eq!(walkability.count_walkable_west(9), 9);
+        assert_eq!(walkability.count_walkable_west(150), 0);
+        assert_eq!(walkability.count_walkable_west(299), 149);
+    }
+
+    #[test]
+    fn it_paths_with_walkability() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let walkability = WalkabilityGrid::new(&grid, 7);
+        assert_eq!(
+            find_path_with_walkability(0, 48, &grid, 7, false, &walkability),
+            find_path(0, 48, &grid, 7, false)
+        );
+        // A free road along the bottom row, which a grid alone would read as
+        // walls.
+        let mut costs = grid.clone();
+        costs[42..49].fill(0);
+        let path = find_path_with_walkability(0, 48, &costs, 7, true, &walkability);
+        assert_eq!(path, vec![7, 14, 21, 28, 35, 42, 43, 44, 45, 46, 47, 48]);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{IndexType, NeighborIdxCache, SearchContext, SearchStats};
+use rayon::prelude::*;
+
+/// Finds a path for every `(start, end)` pair in `queries`, spread across
+/// rayon's threads. Paths are returned in the same order as `queries`.
+pub fn find_paths<N: NeighborIdxCache + Sync + ?Sized>(
+    queries: &[(N::Index, N::Index)],
+    grid: &[u32],
+    width: u32,
+    neighbors: &N,
+) -> Vec<Vec<N::Index>> {
+    SearchContext::new()
+        .find_paths(queries, grid, width, neighbors)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect()
+}
+
+impl<I: IndexType + Send> SearchContext<I> {
+    /// `find_paths`, also returning the stats of each search and adding them
+    /// to the totals. Each of rayon's threads keeps one context for all of its
+    /// searches, reusing its buffers, and their totals are added to this one.
+    pub fn find_paths<N: NeighborIdxCache<Index = I> + Sync + ?Sized>(
+        &mut self,
+        queries: &[(I, I)],
+        grid: &[u32],
+        width: u32,
+        neighbors: &N,
+    ) -> Vec<(Vec<I>, SearchStats)> {
+        let results: Vec<(Vec<I>, SearchStats)> = queries
+            .par_iter()
+            .map_init(SearchContext::new, |context, &(start, end)| {
+                context.find_path(start, end, grid, width, neighbors)
+            })
+            .collect();
+        for (path, stats) in &results {
+            self.record(stats, !path.is_empty());
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_compact_neighbor_idx_cache, create_neighbor_idx_cache, find_path};
+
+    #[test]
+    fn it_finds_paths_in_parallel() {
+        let width = 32;
+        let grid: Vec<u32> = (0..width * width)
+            .map(|idx| (idx % 7 != 3 || idx % width == 0) as u32)
+            .collect();
+        let neighbors = create_neighbor_idx_cache(&grid, width);
+        let queries: Vec<(u32, u32)> = (0..200)
+            .map(|i| ((i * 37) % (width * width), (i * 101 + 5) % (width * width)))
+            .collect();
+        let expected: Vec<Vec<u32>> = queries
+            .iter()
+            .map(|&(start, end)| find_path(start, end, &grid, width, &neighbors))
+            .collect();
+        assert_eq!(find_paths(&queries, &grid, width, &neighbors), expected);
+        let compact = create_compact_neighbor_idx_cache(&grid, width);
+        let mut context = SearchContext::new();
+        let results = context.find_paths(&queries, &grid, width, &compact);
+        assert_eq!(context.searches, 200);
+        assert_eq!(
+            context.totals.expansions,
+            results
+                .iter()
+                .map(|(_, stats)| stats.expansions)
+                .sum::<u64>()
+        );
+        for ((path, _), expected) in results.iter().zip(&expected) {
+            assert_eq!(path, expected);
+        }
+    }
+}
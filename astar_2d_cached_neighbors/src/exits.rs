@@ -0,0 +1,235 @@
+use crate::{
+    create_cardinal_neighbor_idx_cache_with_index_type, create_neighbor_idx_cache_with_index_type,
+    get_cardinal_neighbor_idxs, get_neighbor_idxs, CompactNeighborIdxCache, IndexType,
+};
+
+pub const NORTH: u8 = 1;
+pub const NORTH_EAST: u8 = 1 << 1;
+pub const EAST: u8 = 1 << 2;
+pub const SOUTH_EAST: u8 = 1 << 3;
+pub const SOUTH: u8 = 1 << 4;
+pub const SOUTH_WEST: u8 = 1 << 5;
+pub const WEST: u8 = 1 << 6;
+pub const NORTH_WEST: u8 = 1 << 7;
+pub const ALL_EXITS: u8 = u8::MAX;
+
+/// The exit bit for stepping from `from` to the adjacent tile `to`, or 0 if
+/// they are the same tile.
+#[inline(always)]
+pub fn get_exit_direction(from: u32, to: u32, width: u32) -> u8 {
+    get_exit_bits(from as usize, to as usize, width as usize)
+}
+
+// Works on usize so caches with any index type can use it.
+#[inline(always)]
+fn get_exit_bits(from: usize, to: usize, width: usize) -> u8 {
+    let dx = (to % width) as i64 - (from % width) as i64;
+    let dy = (to / width) as i64 - (from / width) as i64;
+    match (dx.signum(), dy.signum()) {
+        (0, -1) => NORTH,
+        (1, -1) => NORTH_EAST,
+        (1, 0) => EAST,
+        (1, 1) => SOUTH_EAST,
+        (0, 1) => SOUTH,
+        (-1, 1) => SOUTH_WEST,
+        (-1, 0) => WEST,
+        (-1, -1) => NORTH_WEST,
+        _ => 0,
+    }
+}
+
+fn remove_blocked_exits<I: IndexType>(neighbors: &mut [Vec<I>], width: u32, exits: &[u8]) {
+    for (idx, neighbor_idxs) in neighbors.iter_mut().enumerate() {
+        keep_exits(idx, neighbor_idxs, width, exits);
+    }
+}
+
+fn keep_exits<I: IndexType>(idx: usize, neighbor_idxs: &mut Vec<I>, width: u32, exits: &[u8]) {
+    neighbor_idxs.retain(|&neighbor| {
+        let direction = get_exit_bits(idx, neighbor.index(), width as usize);
+        exits[idx] & direction == direction
+    });
+}
+
+/// Like `create_neighbor_idx_cache`, but a tile can only be left in the
+/// directions set in its `exits` bitmask, which allows one-way doors,
+/// conveyors and ledges.
+pub fn create_neighbor_idx_cache_with_exits(
+    grid: &[u32],
+    width: u32,
+    exits: &[u8],
+) -> Vec<Vec<u32>> {
+    create_neighbor_idx_cache_with_exits_and_index_type(grid, width, exits)
+}
+
+/// Like `create_neighbor_idx_cache_with_exits`, with the indices stored as
+/// `I`.
+pub fn create_neighbor_idx_cache_with_exits_and_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+    exits: &[u8],
+) -> Vec<Vec<I>> {
+    let mut neighbor_idx_cache = create_neighbor_idx_cache_with_index_type(grid, width);
+    remove_blocked_exits(&mut neighbor_idx_cache, width, exits);
+    neighbor_idx_cache
+}
+
+/// Like `create_cardinal_neighbor_idx_cache`, but a tile can only be left in
+/// the directions set in its `exits` bitmask.
+pub fn create_cardinal_neighbor_idx_cache_with_exits(
+    grid: &[u32],
+    width: u32,
+    exits: &[u8],
+) -> Vec<Vec<u32>> {
+    create_cardinal_neighbor_idx_cache_with_exits_and_index_type(grid, width, exits)
+}
+
+/// Like `create_cardinal_neighbor_idx_cache_with_exits`, with the indices
+/// stored as `I`.
+pub fn create_cardinal_neighbor_idx_cache_with_exits_and_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+    exits: &[u8],
+) -> Vec<Vec<I>> {
+    let mut neighbor_idx_cache = create_cardinal_neighbor_idx_cache_with_index_type(grid, width);
+    remove_blocked_exits(&mut neighbor_idx_cache, width, exits);
+    neighbor_idx_cache
+}
+
+/// Like `create_neighbor_idx_cache_with_exits`, but builds a
+/// `CompactNeighborIdxCache`.
+pub fn create_compact_neighbor_idx_cache_with_exits(
+    grid: &[u32],
+    width: u32,
+    exits: &[u8],
+) -> CompactNeighborIdxCache {
+    create_compact_neighbor_idx_cache_with_exits_and_index_type(grid, width, exits)
+}
+
+/// Like `create_compact_neighbor_idx_cache_with_exits`, with the indices
+/// stored as `I`.
+pub fn create_compact_neighbor_idx_cache_with_exits_and_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+    exits: &[u8],
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => {
+            let mut neighbors = get_neighbor_idxs(idx, grid, width);
+            keep_exits(idx, &mut neighbors, width, exits);
+            neighbors
+        }
+    })
+}
+
+/// Like `create_cardinal_neighbor_idx_cache_with_exits`, but builds a
+/// `CompactNeighborIdxCache`.
+pub fn create_compact_cardinal_neighbor_idx_cache_with_exits(
+    grid: &[u32],
+    width: u32,
+    exits: &[u8],
+) -> CompactNeighborIdxCache {
+    create_compact_cardinal_neighbor_idx_cache_with_exits_and_index_type(grid, width, exits)
+}
+
+/// Like `create_compact_cardinal_neighbor_idx_cache_with_exits`, with the
+/// indices stored as `I`.
+pub fn create_compact_cardinal_neighbor_idx_cache_with_exits_and_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+    exits: &[u8],
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => {
+            let mut neighbors = get_cardinal_neighbor_idxs(idx, grid, width);
+            keep_exits(idx, &mut neighbors, width, exits);
+            neighbors
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_cardinal_neighbor_idx_cache, find_path};
+
+    #[test]
+    fn it_gets_exit_directions() {
+        assert_eq!(get_exit_direction(4, 1, 3), NORTH);
+        assert_eq!(get_exit_direction(4, 2, 3), NORTH_EAST);
+        assert_eq!(get_exit_direction(4, 5, 3), EAST);
+        assert_eq!(get_exit_direction(4, 8, 3), SOUTH_EAST);
+        assert_eq!(get_exit_direction(4, 7, 3), SOUTH);
+        assert_eq!(get_exit_direction(4, 6, 3), SOUTH_WEST);
+        assert_eq!(get_exit_direction(4, 3, 3), WEST);
+        assert_eq!(get_exit_direction(4, 0, 3), NORTH_WEST);
+        assert_eq!(get_exit_direction(4, 4, 3), 0);
+    }
+
+    #[test]
+    fn it_only_leaves_tiles_through_their_exits() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        let all_exits = vec![ALL_EXITS; grid.len()];
+        assert_eq!(
+            create_cardinal_neighbor_idx_cache_with_exits(&grid, 5, &all_exits),
+            create_cardinal_neighbor_idx_cache(&grid, 5)
+        );
+        // The middle row is a conveyor that only runs east.
+        let mut exits = all_exits.clone();
+        exits[5..10].fill(EAST);
+        let neighbors = create_cardinal_neighbor_idx_cache_with_exits(&grid, 5, &exits);
+        assert_eq!(neighbors[7], vec![8]);
+        assert_eq!(neighbors[9], vec![]);
+        assert_eq!(find_path(5, 9, &grid, 5, &neighbors), vec![6, 7, 8, 9]);
+        assert_eq!(find_path(9, 5, &grid, 5, &neighbors), vec![]);
+        let neighbors = create_neighbor_idx_cache_with_exits(&grid, 5, &exits);
+        let path = find_path(14, 10, &grid, 5, &neighbors);
+        assert!(path.iter().all(|&idx| idx >= 10));
+        assert_eq!(path.last(), Some(&10));
+    }
+
+    #[test]
+    fn it_builds_exit_caches_with_other_index_types() {
+        let grid = vec![1; 15];
+        let mut exits = vec![ALL_EXITS; grid.len()];
+        exits[5..10].fill(EAST);
+        let neighbors =
+            create_neighbor_idx_cache_with_exits_and_index_type::<u16>(&grid, 5, &exits);
+        assert_eq!(neighbors[7], vec![8]);
+        assert_eq!(find_path(5, 9, &grid, 5, &neighbors), vec![6, 7, 8, 9]);
+        let neighbors =
+            create_cardinal_neighbor_idx_cache_with_exits_and_index_type::<u64>(&grid, 5, &exits);
+        assert_eq!(neighbors[9], vec![]);
+        assert_eq!(find_path(9, 5, &grid, 5, &neighbors), vec![]);
+    }
+    #[test]
+    fn it_builds_compact_exit_caches() {
+        let grid = vec![1; 15];
+        let mut exits = vec![ALL_EXITS; grid.len()];
+        exits[5..10].fill(EAST);
+        let compact = create_compact_cardinal_neighbor_idx_cache_with_exits(&grid, 5, &exits);
+        assert_eq!(
+            compact,
+            CompactNeighborIdxCache::from_neighbor_idx_cache(
+                &create_cardinal_neighbor_idx_cache_with_exits(&grid, 5, &exits)
+            )
+        );
+        assert_eq!(find_path(9, 5, &grid, 5, &compact), vec![]);
+        let compact =
+            create_compact_neighbor_idx_cache_with_exits_and_index_type::<u16>(&grid, 5, &exits);
+        assert_eq!(
+            compact,
+            CompactNeighborIdxCache::from_neighbor_idx_cache(
+                &create_neighbor_idx_cache_with_exits_and_index_type(&grid, 5, &exits)
+            )
+        );
+        assert_eq!(find_path(5, 9, &grid, 5, &compact), vec![6, 7, 8, 9]);
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// A type tile indices are stored in. `u16` halves the memory of a neighbor
+/// cache for maps of up to 65536 tiles, and `u64` allows maps with more than
+/// `u32::MAX` tiles.
+pub trait IndexType: Copy + Ord + Hash + Debug + Send + Sync {
+    /// Converts a tile index, panicking if the type can't hold it.
+    fn new(idx: usize) -> Self;
+
+    /// Converts a tile index, or returns `None` if the type can't hold it.
+    fn try_new(idx: usize) -> Option<Self>;
+
+    fn index(self) -> usize;
+}
+
+macro_rules! impl_index_type {
+    ($($index:ty),*) => {
+        $(
+            impl IndexType for $index {
+                #[inline(always)]
+                fn new(idx: usize) -> Self {
+                    match Self::try_new(idx) {
+                        Some(idx) => idx,
+                        None => panic!(
+                            "tile index {} does not fit in {}",
+                            idx,
+                            stringify!($index)
+                        ),
+                    }
+                }
+
+                #[inline(always)]
+                fn try_new(idx: usize) -> Option<Self> {
+                    <$index>::try_from(idx).ok()
+                }
+
+                #[inline(always)]
+                fn index(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_index_type!(u16, u32, u64, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_converts_indices() {
+        assert_eq!(u16::new(65535), u16::MAX);
+        assert_eq!(u64::new(7).index(), 7);
+        assert_eq!(u32::new(12).index(), 12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_indices_that_do_not_fit() {
+        u16::new(65536);
+    }
+}
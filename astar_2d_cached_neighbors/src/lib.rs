@@ -1,202 +1,520 @@
 #![feature(test)]
 extern crate test;
 
+#[cfg(feature = "rayon")]
+mod batch;
+mod clearance;
+mod cost;
+pub mod exits;
+mod index;
+mod line_of_sight;
+mod neighbor_cache;
+mod observer;
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel_cache;
+mod smoothing;
+mod stats;
+mod terrain;
+
+#[cfg(feature = "rayon")]
+pub use batch::find_paths;
+pub use clearance::{create_clearance_map, find_path_with_clearance};
+pub use cost::{Cost, Fixed, OrderedFloat, OverflowPolicy};
+pub use exits::{
+    create_cardinal_neighbor_idx_cache_with_exits,
+    create_cardinal_neighbor_idx_cache_with_exits_and_index_type,
+    create_compact_cardinal_neighbor_idx_cache_with_exits,
+    create_compact_cardinal_neighbor_idx_cache_with_exits_and_index_type,
+    create_compact_neighbor_idx_cache_with_exits,
+    create_compact_neighbor_idx_cache_with_exits_and_index_type,
+    create_neighbor_idx_cache_with_exits, create_neighbor_idx_cache_with_exits_and_index_type,
+    get_exit_direction,
+};
+pub use index::IndexType;
+pub use line_of_sight::{field_of_view, has_line_of_sight, raycast};
+pub use neighbor_cache::{
+    CacheFormatError, CompactNeighborIdxCache, NeighborIdxCache, CACHE_FORMAT_VERSION,
+};
+pub use observer::{find_path_with_observer, SearchObserver};
+pub use options::{SearchOptions, TieBreaking};
+#[cfg(feature = "rayon")]
+pub use parallel_cache::{
+    par_create_cardinal_neighbor_idx_cache, par_create_cardinal_neighbor_idx_cache_with_index_type,
+    par_create_compact_cardinal_neighbor_idx_cache,
+    par_create_compact_cardinal_neighbor_idx_cache_with_index_type,
+    par_create_compact_neighbor_idx_cache, par_create_compact_neighbor_idx_cache_with_index_type,
+    par_create_neighbor_idx_cache, par_create_neighbor_idx_cache_with_index_type,
+};
+pub use smoothing::{remove_collinear_points, smooth_path};
+pub use stats::{measure, SearchContext, SearchStats, StatsObserver};
+pub use terrain::{find_path_for_agent, AgentProfile};
+
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct FrontierItem {
-    pub position: u32,
-    pub cost: u32,
+struct FrontierItem<C = u32, I = u32> {
+    pub position: I,
+    pub cost: C,
+    pub tie_breaker: u64,
 }
 
-impl Ord for FrontierItem {
+impl<C: Ord, I: Ord> Ord for FrontierItem<C, I> {
     fn cmp(&self, other: &Self) -> Ordering {
         other
             .cost
             .cmp(&self.cost)
+            .then_with(|| other.tie_breaker.cmp(&self.tie_breaker))
             .then_with(|| self.position.cmp(&other.position))
     }
 }
 
-impl PartialOrd for FrontierItem {
+impl<C: Ord, I: Ord> PartialOrd for FrontierItem<C, I> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-fn get_neighbor_idxs(current: u32, grid: &Vec<u32>, width: u32) -> Vec<u32> {
+// What a search works in. A `SearchContext` keeps its buffers between searches
+// so that running many of them allocates once rather than for every search.
+pub(crate) struct SearchBuffers<C, I> {
+    frontier: BinaryHeap<FrontierItem<C, I>>,
+    cost_so_far: Vec<Option<C>>,
+    came_from: Vec<I>,
+    closed: Vec<bool>,
+}
+
+impl<C: Ord, I: Ord> Default for SearchBuffers<C, I> {
+    fn default() -> Self {
+        Self {
+            frontier: BinaryHeap::new(),
+            cost_so_far: vec![],
+            came_from: vec![],
+            closed: vec![],
+        }
+    }
+}
+
+impl<C: Cost, I: IndexType> SearchBuffers<C, I> {
+    // Clears whatever the last search left behind, sized for `tile_count` tiles.
+    fn reset(&mut self, tile_count: usize, start: I) {
+        self.frontier.clear();
+        self.frontier.reserve(tile_count);
+        self.cost_so_far.clear();
+        self.cost_so_far.resize(tile_count, None);
+        self.came_from.clear();
+        self.came_from.resize(tile_count, start);
+        self.closed.clear();
+        self.closed.resize(tile_count, false);
+    }
+}
+
+fn get_neighbor_idxs<I: IndexType>(current: usize, grid: &[u32], width: u32) -> Vec<I> {
+    let width = width as usize;
     let is_top = current < width;
-    let is_bottom = current >= grid.len() as u32 - width;
+    let is_bottom = current >= grid.len() - width;
     let x = current % width;
     let is_left = x == 0;
     let is_right = x == width - 1;
-    let mut neighbors: Vec<u32> = vec![];
+    let mut neighbors: Vec<I> = vec![];
     if !is_top {
         let top_index = current - width;
-        if grid[top_index as usize] > 0 {
-            neighbors.push(top_index)
+        if grid[top_index] > 0 {
+            neighbors.push(I::new(top_index))
         }
-        if !is_left && grid[top_index as usize - 1] > 0 {
-            neighbors.push(top_index - 1)
+        if !is_left && grid[top_index - 1] > 0 {
+            neighbors.push(I::new(top_index - 1))
         }
-        if !is_right && grid[top_index as usize + 1] > 0 {
-            neighbors.push(top_index + 1)
+        if !is_right && grid[top_index + 1] > 0 {
+            neighbors.push(I::new(top_index + 1))
         }
     }
-    if !is_left && grid[current as usize - 1] > 0 {
-        neighbors.push(current - 1)
+    if !is_left && grid[current - 1] > 0 {
+        neighbors.push(I::new(current - 1))
     }
-    if !is_right && grid[current as usize + 1] > 0 {
-        neighbors.push(current + 1)
+    if !is_right && grid[current + 1] > 0 {
+        neighbors.push(I::new(current + 1))
     }
     if !is_bottom {
         let bottom_index = current + width;
-        if grid[bottom_index as usize] > 0 {
-            neighbors.push(bottom_index)
+        if grid[bottom_index] > 0 {
+            neighbors.push(I::new(bottom_index))
         }
-        if !is_left && grid[bottom_index as usize - 1] > 0 {
-            neighbors.push(bottom_index - 1)
+        if !is_left && grid[bottom_index - 1] > 0 {
+            neighbors.push(I::new(bottom_index - 1))
         }
-        if !is_right && grid[bottom_index as usize + 1] > 0 {
-            neighbors.push(bottom_index + 1)
+        if !is_right && grid[bottom_index + 1] > 0 {
+            neighbors.push(I::new(bottom_index + 1))
         }
     }
     neighbors
 }
 
-fn get_cardinal_neighbor_idxs(current: u32, grid: &Vec<u32>, width: u32) -> Vec<u32> {
+fn get_cardinal_neighbor_idxs<I: IndexType>(current: usize, grid: &[u32], width: u32) -> Vec<I> {
+    let width = width as usize;
     let is_top = current < width;
-    let is_bottom = current >= grid.len() as u32 - width;
+    let is_bottom = current >= grid.len() - width;
     let x = current % width;
     let is_left = x == 0;
     let is_right = x == width - 1;
-    let mut neighbors: Vec<u32> = vec![];
+    let mut neighbors: Vec<I> = vec![];
     if !is_top {
         let top_index = current - width;
-        if grid[top_index as usize] > 0 {
-            neighbors.push(top_index)
+        if grid[top_index] > 0 {
+            neighbors.push(I::new(top_index))
         }
     }
-    if !is_left && grid[current as usize - 1] > 0 {
-        neighbors.push(current - 1)
+    if !is_left && grid[current - 1] > 0 {
+        neighbors.push(I::new(current - 1))
     }
-    if !is_right && grid[current as usize + 1] > 0 {
-        neighbors.push(current + 1)
+    if !is_right && grid[current + 1] > 0 {
+        neighbors.push(I::new(current + 1))
     }
     if !is_bottom {
         let bottom_index = current + width;
-        if grid[bottom_index as usize] > 0 {
-            neighbors.push(bottom_index)
+        if grid[bottom_index] > 0 {
+            neighbors.push(I::new(bottom_index))
         }
     }
     neighbors
 }
 
-pub fn create_neighbor_idx_cache(grid: &Vec<u32>, width: u32) -> Vec<Vec<u32>> {
+pub fn create_neighbor_idx_cache(grid: &[u32], width: u32) -> Vec<Vec<u32>> {
+    create_neighbor_idx_cache_with_index_type(grid, width)
+}
+
+/// Like `create_neighbor_idx_cache`, with the indices stored as `I`. Every
+/// function that takes the cache uses the same index type for tiles.
+pub fn create_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+) -> Vec<Vec<I>> {
     let mut neighbor_idx_cache = vec![];
     for idx in 0..grid.len() {
         let neighbors = if grid[idx] == 0 {
             vec![]
         } else {
-            get_neighbor_idxs(idx as u32, grid, width)
+            get_neighbor_idxs(idx, grid, width)
         };
         neighbor_idx_cache.push(neighbors);
     }
     neighbor_idx_cache
 }
 
-pub fn create_cardinal_neighbor_idx_cache(grid: &Vec<u32>, width: u32) -> Vec<Vec<u32>> {
+pub fn create_cardinal_neighbor_idx_cache(grid: &[u32], width: u32) -> Vec<Vec<u32>> {
+    create_cardinal_neighbor_idx_cache_with_index_type(grid, width)
+}
+
+/// Like `create_cardinal_neighbor_idx_cache`, with the indices stored as `I`.
+pub fn create_cardinal_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+) -> Vec<Vec<I>> {
     let mut neighbor_idx_cache = vec![];
     for idx in 0..grid.len() {
         let neighbors = if grid[idx] == 0 {
             vec![]
         } else {
-            get_cardinal_neighbor_idxs(idx as u32, grid, width)
+            get_cardinal_neighbor_idxs(idx, grid, width)
         };
         neighbor_idx_cache.push(neighbors);
     }
     neighbor_idx_cache
 }
 
+/// Like `create_neighbor_idx_cache`, but builds a `CompactNeighborIdxCache`.
+pub fn create_compact_neighbor_idx_cache(grid: &[u32], width: u32) -> CompactNeighborIdxCache {
+    create_compact_neighbor_idx_cache_with_index_type(grid, width)
+}
+
+/// Like `create_compact_neighbor_idx_cache`, with the indices stored as `I`.
+pub fn create_compact_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => get_neighbor_idxs(idx, grid, width),
+    })
+}
+
+/// Like `create_cardinal_neighbor_idx_cache`, but builds a
+/// `CompactNeighborIdxCache`.
+pub fn create_compact_cardinal_neighbor_idx_cache(
+    grid: &[u32],
+    width: u32,
+) -> CompactNeighborIdxCache {
+    create_compact_cardinal_neighbor_idx_cache_with_index_type(grid, width)
+}
+
+/// Like `create_compact_cardinal_neighbor_idx_cache`, with the indices stored
+/// as `I`.
+pub fn create_compact_cardinal_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => get_cardinal_neighbor_idxs(idx, grid, width),
+    })
+}
+
 #[inline(always)]
 fn manhattan(x1: i32, y1: i32, x2: i32, y2: i32) -> u32 {
     ((x1 - x2).abs() + (y1 - y2).abs()) as u32
 }
 
-pub fn find_path(
-    start: u32,
-    end: u32,
-    grid: &Vec<u32>,
+pub(crate) fn search<
+    N: NeighborIdxCache + ?Sized,
+    C: Cost,
+    F: Fn(N::Index, N::Index) -> Option<C>,
+    O: SearchObserver<C, N::Index>,
+>(
+    start: N::Index,
+    end: N::Index,
+    width: u32,
+    neighbors: &N,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+    observer: O,
+) -> Vec<N::Index> {
+    search_with_buffers(
+        start,
+        end,
+        width,
+        neighbors,
+        get_cost_for_edge,
+        options,
+        observer,
+        &mut SearchBuffers::default(),
+    )
+}
+
+// Like `search`, working in `buffers` rather than allocating its own.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn search_with_buffers<
+    N: NeighborIdxCache + ?Sized,
+    C: Cost,
+    F: Fn(N::Index, N::Index) -> Option<C>,
+    O: SearchObserver<C, N::Index>,
+>(
+    start: N::Index,
+    end: N::Index,
     width: u32,
-    neighbors: &Vec<Vec<u32>>,
-) -> Vec<u32> {
-    let end_x = end % width;
-    let end_y = end / width;
-    let mut frontier = BinaryHeap::with_capacity(grid.len());
-    let mut cost_so_far = vec![0; grid.len()];
-    let mut came_from = vec![start; grid.len()];
-    cost_so_far[start as usize] = 1;
+    neighbors: &N,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+    mut observer: O,
+    buffers: &mut SearchBuffers<C, N::Index>,
+) -> Vec<N::Index> {
+    let to_xy = |idx: N::Index| {
+        (
+            (idx.index() % width as usize) as i32,
+            (idx.index() / width as usize) as i32,
+        )
+    };
+    let (end_x, end_y) = to_xy(end);
+    buffers.reset(neighbors.tile_count(), start);
+    let SearchBuffers {
+        frontier,
+        cost_so_far,
+        came_from,
+        closed,
+    } = buffers;
+    cost_so_far[start.index()] = Some(C::ZERO);
+    observer.on_push(start, C::ZERO);
     frontier.push(FrontierItem {
-        cost: 0,
+        cost: C::ZERO,
+        tie_breaker: 0,
         position: start,
     });
     let mut found = false;
     while !frontier.is_empty() {
         let current_position = frontier.pop().unwrap().position;
+        // Tiles improved after being queued leave stale entries behind.
+        if closed[current_position.index()] {
+            observer.on_skip(current_position);
+            continue;
+        }
+        closed[current_position.index()] = true;
         if current_position == end {
             found = true;
             break;
         }
-        let current_x = current_position % width;
-        let current_y = current_position / width;
-        let neighbor_coords = &neighbors[current_position as usize];
-        for idx in 0..neighbor_coords.len() {
-            let neighbor = neighbor_coords[idx];
-            let neighbor_cost = grid[neighbor as usize];
-            let neighbor_x = neighbor % width;
-            let neighbor_y = neighbor / width;
-            let cost = cost_so_far[current_position as usize]
-                + neighbor_cost
-                + manhattan(
-                    current_x as i32,
-                    current_y as i32,
-                    neighbor_x as i32,
-                    neighbor_y as i32,
-                );
-            let neighbor_cost_so_far = cost_so_far[neighbor as usize];
-            if neighbor_cost_so_far == 0 || cost < neighbor_cost_so_far {
-                cost_so_far[neighbor as usize] = cost;
-                let priority = cost
-                    + manhattan(
-                        end_x as i32,
-                        end_y as i32,
-                        neighbor_x as i32,
-                        neighbor_y as i32,
-                    );
+        let current_cost = cost_so_far[current_position.index()].expect("queued tiles have a cost");
+        observer.on_expand(current_position, current_cost);
+        let (current_x, current_y) = to_xy(current_position);
+        let neighbor_coords = neighbors.get_neighbors(current_position);
+        for &neighbor in neighbor_coords {
+            if closed[neighbor.index()] {
+                continue;
+            }
+            let neighbor_cost = match get_cost_for_edge(current_position, neighbor) {
+                Some(cost) => cost,
+                None => continue,
+            };
+            let (neighbor_x, neighbor_y) = to_xy(neighbor);
+            let distance =
+                C::from_tiles(manhattan(current_x, current_y, neighbor_x, neighbor_y) as f64);
+            let cost = match options
+                .overflow
+                .add(current_cost, neighbor_cost)
+                .and_then(|cost| options.overflow.add(cost, distance))
+            {
+                Some(cost) => cost,
+                None => continue,
+            };
+            let is_cheaper = match cost_so_far[neighbor.index()] {
+                Some(neighbor_cost_so_far) if cost < neighbor_cost_so_far => {
+                    observer.on_relax(neighbor, neighbor_cost_so_far, cost);
+                    true
+                }
+                Some(_) => false,
+                None => true,
+            };
+            if is_cheaper {
+                cost_so_far[neighbor.index()] = Some(cost);
+                observer.on_push(neighbor, cost);
+                let heuristic = manhattan(end_x, end_y, neighbor_x, neighbor_y);
+                let priority = cost.saturating_add(C::from_tiles(
+                    heuristic as f64 * options.heuristic_weight as f64,
+                ));
                 frontier.push(FrontierItem {
                     cost: priority,
+                    tie_breaker: options.tie_breaking.rank(
+                        cost.order_key(),
+                        heuristic,
+                        neighbor.index() as u64,
+                        start.index() as u64,
+                        end.index() as u64,
+                        width,
+                    ),
                     position: neighbor,
                 });
-                came_from[neighbor as usize] = current_position;
+                came_from[neighbor.index()] = current_position;
             }
         }
     }
-    let mut path: Vec<u32> = Vec::new();
+    let mut path: Vec<N::Index> = Vec::new();
     if found {
         let mut last = end;
         loop {
             path.push(last);
-            last = came_from[last as usize];
+            last = came_from[last.index()];
             if last == start {
                 break;
             }
         }
         path.reverse();
+        observer.on_path_found(&path);
     }
     path
 }
 
+/// Like `find_path`, but the cost of stepping from one tile to a neighbor is
+/// `get_cost_for_edge(from, to)` rather than a value read from a grid.
+/// Returning `None` forbids the step. `neighbors` still decides which tiles
+/// are connected, and the distance moved is added on top of the returned cost.
+/// Returning `Some(0)` makes a step free, such as along a road.
+pub fn find_path_with_edge_cost<
+    N: NeighborIdxCache + ?Sized,
+    C: Cost,
+    F: Fn(N::Index, N::Index) -> Option<C>,
+>(
+    start: N::Index,
+    end: N::Index,
+    width: u32,
+    neighbors: &N,
+    get_cost_for_edge: F,
+) -> Vec<N::Index> {
+    search(
+        start,
+        end,
+        width,
+        neighbors,
+        get_cost_for_edge,
+        &SearchOptions::default(),
+        (),
+    )
+}
+
+/// Like `find_path_with_edge_cost`, with the search tuned by `options`.
+pub fn find_path_with_edge_cost_and_options<
+    N: NeighborIdxCache + ?Sized,
+    C: Cost,
+    F: Fn(N::Index, N::Index) -> Option<C>,
+>(
+    start: N::Index,
+    end: N::Index,
+    width: u32,
+    neighbors: &N,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+) -> Vec<N::Index> {
+    search(start, end, width, neighbors, get_cost_for_edge, options, ())
+}
+
+/// Finds the cheapest path from `start` to `end`. `neighbors` can be any
+/// `NeighborIdxCache`, and tiles are indexed with its index type.
+pub fn find_path<N: NeighborIdxCache + ?Sized>(
+    start: N::Index,
+    end: N::Index,
+    grid: &[u32],
+    width: u32,
+    neighbors: &N,
+) -> Vec<N::Index> {
+    find_path_with_edge_cost(start, end, width, neighbors, |_, idx: N::Index| {
+        Some(grid[idx.index()])
+    })
+}
+
+/// Weighted A*: like `find_path`, but the heuristic is multiplied by
+/// `heuristic_weight`. Weights above 1 expand fewer tiles in exchange for
+/// paths that may cost up to `heuristic_weight` times the cheapest one.
+pub fn find_path_weighted<N: NeighborIdxCache + ?Sized>(
+    start: N::Index,
+    end: N::Index,
+    grid: &[u32],
+    width: u32,
+    neighbors: &N,
+    heuristic_weight: f32,
+) -> Vec<N::Index> {
+    search(
+        start,
+        end,
+        width,
+        neighbors,
+        |_, idx: N::Index| Some(grid[idx.index()]),
+        &SearchOptions {
+            heuristic_weight,
+            ..SearchOptions::default()
+        },
+        (),
+    )
+}
+
+/// Like `find_path`, with the heuristic weight and tie-breaking set by
+/// `options`.
+pub fn find_path_with_options<N: NeighborIdxCache + ?Sized>(
+    start: N::Index,
+    end: N::Index,
+    grid: &[u32],
+    width: u32,
+    neighbors: &N,
+    options: &SearchOptions,
+) -> Vec<N::Index> {
+    search(
+        start,
+        end,
+        width,
+        neighbors,
+        |_, idx: N::Index| Some(grid[idx.index()]),
+        options,
+        (),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -310,6 +628,218 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_uses_edge_costs() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, 3);
+        let path = find_path_with_edge_cost(0, 2, 3, &neighbors, |_, to| Some(grid[to as usize]));
+        assert_eq!(path, vec![1, 2]);
+        let path = find_path_with_edge_cost(0, 2, 3, &neighbors, |_, to| match to {
+            1 => Some(10),
+            _ => Some(grid[to as usize]),
+        });
+        assert_eq!(path, vec![4, 2]);
+        let path = find_path_with_edge_cost(0, 2, 3, &neighbors, |from, to| match (from, to) {
+            (1, 2) => None,
+            _ => Some(grid[to as usize]),
+        });
+        assert_eq!(path, vec![4, 2]);
+    }
+
+    #[test]
+    fn it_paths_over_zero_cost_tiles() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        // The bottom row is a road, which is free to enter apart from the
+        // distance moved.
+        #[rustfmt::skip]
+        let costs: Vec<u32> = vec![
+            1, 9, 1,
+            0, 0, 0,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, 3);
+        let path = find_path_with_edge_cost(0, 2, 3, &neighbors, |_, to| Some(costs[to as usize]));
+        assert!(!path.contains(&1));
+        assert_eq!(path.last(), Some(&2));
+        let path = find_path_with_edge_cost(0, 2, 3, &neighbors, |_, _| Some(0u32));
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[test]
+    fn it_weights_the_heuristic() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, 7);
+        let path = find_path_weighted(0, 48, &grid, 7, &neighbors, 1.0);
+        assert_eq!(path, find_path(0, 48, &grid, 7, &neighbors));
+        let path = find_path_weighted(0, 48, &grid, 7, &neighbors, 3.0);
+        assert_eq!(path.last(), Some(&48));
+    }
+
+    #[test]
+    fn it_accumulates_other_cost_types() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, 3);
+        let path = find_path_with_edge_cost(0, 2, 3, &neighbors, |_, to| {
+            Some(Fixed::from_f64(if to == 1 { 2.5 } else { 0.25 }))
+        });
+        assert_eq!(path, vec![4, 2]);
+        let path = find_path_with_edge_cost(0, 2, 3, &neighbors, |_, to| {
+            Some(OrderedFloat(if to == 1 { 2.0 } else { 0.25 }))
+        });
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[test]
+    fn it_handles_cost_overflow() {
+        let grid = vec![1, 1, 1];
+        let neighbors = create_neighbor_idx_cache(&grid, 3);
+        let get_cost_for_edge = |_, to| Some(if to == 1 { u16::MAX } else { 1 });
+        let saturating = SearchOptions {
+            overflow: OverflowPolicy::Saturating,
+            ..SearchOptions::default()
+        };
+        let path = find_path_with_edge_cost_and_options(
+            0,
+            2,
+            3,
+            &neighbors,
+            get_cost_for_edge,
+            &SearchOptions::default(),
+        );
+        assert_eq!(path, vec![]);
+        let path = find_path_with_edge_cost_and_options(
+            0,
+            2,
+            3,
+            &neighbors,
+            get_cost_for_edge,
+            &saturating,
+        );
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[test]
+    fn it_uses_other_index_types() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache_with_index_type::<u16>(&grid, 7);
+        let path = find_path(0, 48, &grid, 7, &neighbors);
+        assert_eq!(path, vec![8, 15, 22, 29, 37, 45, 46, 47, 48]);
+        let neighbors = create_cardinal_neighbor_idx_cache_with_index_type::<u64>(&grid, 7);
+        let path = find_path(0, 48, &grid, 7, &neighbors);
+        let expected = find_path(
+            0,
+            48,
+            &grid,
+            7,
+            &create_cardinal_neighbor_idx_cache(&grid, 7),
+        );
+        assert_eq!(
+            path,
+            expected.iter().map(|&idx| idx as u64).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_uses_compact_neighbor_caches() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let neighbors = create_compact_neighbor_idx_cache(&grid, 7);
+        let path = find_path(0, 48, &grid, 7, &neighbors);
+        assert_eq!(path, vec![8, 15, 22, 29, 37, 45, 46, 47, 48]);
+        let neighbors = create_compact_cardinal_neighbor_idx_cache_with_index_type::<u16>(&grid, 7);
+        let path = find_path(0, 48, &grid, 7, &neighbors);
+        let expected = find_path(
+            0,
+            48,
+            &grid,
+            7,
+            &create_cardinal_neighbor_idx_cache(&grid, 7),
+        );
+        assert_eq!(
+            path,
+            expected.iter().map(|&idx| idx as u16).collect::<Vec<_>>()
+        );
+        let neighbors = CompactNeighborIdxCache::from_neighbor_idx_cache(
+            &create_neighbor_idx_cache_with_exits(&grid, 7, &vec![exits::ALL_EXITS; grid.len()]),
+        );
+        let path = find_path(0, 48, &grid, 7, &neighbors);
+        assert_eq!(path, vec![8, 15, 22, 29, 37, 45, 46, 47, 48]);
+    }
+
+    #[bench]
+    fn bench_it_crosses_rough_ground(b: &mut Bencher) {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 9, 1,
+            9, 9, 9, 9, 9, 9, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 9, 9, 9, 9, 9, 9,
+            1, 1, 1, 1, 1, 1, 1,
+            9, 1, 1, 1, 1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, 7);
+        let (_, stats) =
+            measure(|observer| find_path_with_observer(0, 48, &grid, 7, &neighbors, observer));
+        assert_eq!((stats.expansions, stats.stale_entries), (41, 10));
+        b.iter(|| find_path(0, 48, &grid, 7, &neighbors));
+    }
+
+    #[bench]
+    fn bench_it_avoids_walls_with_a_compact_cache(b: &mut Bencher) {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let neighbors = create_compact_neighbor_idx_cache(&grid, 7);
+        b.iter(|| find_path(0, 48, &grid, 7, &neighbors));
+    }
+
     #[bench]
     fn bench_it_avoids_walls(b: &mut Bencher) {
         #[rustfmt::skip]
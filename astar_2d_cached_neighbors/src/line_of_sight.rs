@@ -0,0 +1,329 @@
+use std::cmp::Ordering;
+
+#[inline(always)]
+fn is_open(x: i32, y: i32, grid: &[u32], width: u32) -> bool {
+    grid[(y as u32 * width + x as u32) as usize] > 0
+}
+
+#[inline(always)]
+fn to_idx(x: i32, y: i32, width: u32) -> u32 {
+    y as u32 * width + x as u32
+}
+
+// Bresenham line between tile centres. Every step is a legal move for the
+// 8-directional neighbor rules, which allow diagonals past wall corners.
+fn walk_line_diagonal<F: FnMut(u32)>(
+    from: u32,
+    to: u32,
+    grid: &[u32],
+    width: u32,
+    mut visit: F,
+) -> Option<u32> {
+    let (mut x, mut y) = ((from % width) as i32, (from / width) as i32);
+    let (end_x, end_y) = ((to % width) as i32, (to / width) as i32);
+    let dx = (end_x - x).abs();
+    let dy = -(end_y - y).abs();
+    let step_x = if x < end_x { 1 } else { -1 };
+    let step_y = if y < end_y { 1 } else { -1 };
+    let mut error = dx + dy;
+    if !is_open(x, y, grid, width) {
+        return Some(from);
+    }
+    while x != end_x || y != end_y {
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+        if !is_open(x, y, grid, width) {
+            return Some(to_idx(x, y, width));
+        }
+        visit(to_idx(x, y, width));
+    }
+    None
+}
+
+// 4-connected walk between tile centres. When the line passes exactly through
+// a tile corner either of the two tiles beside it may be used to get around.
+fn walk_line_cardinal<F: FnMut(u32)>(
+    from: u32,
+    to: u32,
+    grid: &[u32],
+    width: u32,
+    mut visit: F,
+) -> Option<u32> {
+    let (mut x, mut y) = ((from % width) as i32, (from / width) as i32);
+    let (end_x, end_y) = ((to % width) as i32, (to / width) as i32);
+    let steps_x = (end_x - x).abs();
+    let steps_y = (end_y - y).abs();
+    let step_x = if x < end_x { 1 } else { -1 };
+    let step_y = if y < end_y { 1 } else { -1 };
+    if !is_open(x, y, grid, width) {
+        return Some(from);
+    }
+    let (mut taken_x, mut taken_y) = (0, 0);
+    while taken_x < steps_x || taken_y < steps_y {
+        let decision = (1 + 2 * taken_x) * steps_y - (1 + 2 * taken_y) * steps_x;
+        match decision.cmp(&0) {
+            Ordering::Equal => {
+                if is_open(x + step_x, y, grid, width) {
+                    visit(to_idx(x + step_x, y, width));
+                } else if is_open(x, y + step_y, grid, width) {
+                    visit(to_idx(x, y + step_y, width));
+                } else {
+                    return Some(to_idx(x + step_x, y, width));
+                }
+                x += step_x;
+                y += step_y;
+                taken_x += 1;
+                taken_y += 1;
+            }
+            Ordering::Less => {
+                x += step_x;
+                taken_x += 1;
+            }
+            Ordering::Greater => {
+                y += step_y;
+                taken_y += 1;
+            }
+        }
+        if !is_open(x, y, grid, width) {
+            return Some(to_idx(x, y, width));
+        }
+        visit(to_idx(x, y, width));
+    }
+    None
+}
+
+// Calls `visit` with every tile after `from` on the line to `to`, in order,
+// stopping at and returning the first wall.
+pub(crate) fn walk_line<F: FnMut(u32)>(
+    from: u32,
+    to: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+    visit: F,
+) -> Option<u32> {
+    match cardinal_directions {
+        true => walk_line_cardinal(from, to, grid, width, visit),
+        _ => walk_line_diagonal(from, to, grid, width, visit),
+    }
+}
+
+/// Walks the straight line from `from` to `to` using the same corner cutting
+/// rules as `find_path`, returning the first wall hit or `None` if `to` is
+/// reached.
+pub fn raycast(
+    from: u32,
+    to: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+) -> Option<u32> {
+    walk_line(from, to, grid, width, cardinal_directions, |_| {})
+}
+
+pub fn has_line_of_sight(
+    from: u32,
+    to: u32,
+    grid: &[u32],
+    width: u32,
+    cardinal_directions: bool,
+) -> bool {
+    raycast(from, to, grid, width, cardinal_directions).is_none()
+}
+
+// Multipliers transforming the first octant into each of the eight octants.
+const OCTANTS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+#[allow(clippy::too_many_arguments)]
+fn cast_light(
+    origin: (i32, i32),
+    row: i32,
+    mut start_slope: f32,
+    end_slope: f32,
+    radius: i32,
+    octant: (i32, i32, i32, i32),
+    grid: &[u32],
+    width: u32,
+    visible: &mut Vec<bool>,
+) {
+    if start_slope < end_slope {
+        return;
+    }
+    let height = (grid.len() as u32 / width) as i32;
+    let (xx, xy, yx, yy) = octant;
+    let mut next_start_slope = start_slope;
+    for distance in row..=radius {
+        let dy = -distance;
+        let mut blocked = false;
+        for dx in -distance..=0 {
+            let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+            if start_slope < right_slope {
+                continue;
+            }
+            if end_slope > left_slope {
+                break;
+            }
+            let x = origin.0 + dx * xx + dy * xy;
+            let y = origin.1 + dx * yx + dy * yy;
+            if x < 0 || y < 0 || x >= width as i32 || y >= height {
+                continue;
+            }
+            if dx * dx + dy * dy <= radius * radius {
+                visible[to_idx(x, y, width) as usize] = true;
+            }
+            let is_wall = !is_open(x, y, grid, width);
+            if blocked {
+                if is_wall {
+                    next_start_slope = right_slope;
+                } else {
+                    blocked = false;
+                    start_slope = next_start_slope;
+                }
+            } else if is_wall && distance < radius {
+                blocked = true;
+                cast_light(
+                    origin,
+                    distance + 1,
+                    start_slope,
+                    left_slope,
+                    radius,
+                    octant,
+                    grid,
+                    width,
+                    visible,
+                );
+                next_start_slope = right_slope;
+            }
+        }
+        if blocked {
+            break;
+        }
+    }
+}
+
+/// Recursive shadowcasting from `origin`. Returns every tile within `radius`
+/// that can be seen, including the walls that block the view, in index order.
+pub fn field_of_view(origin: u32, radius: u32, grid: &[u32], width: u32) -> Vec<u32> {
+    let mut visible = vec![false; grid.len()];
+    visible[origin as usize] = true;
+    let origin_xy = ((origin % width) as i32, (origin / width) as i32);
+    for octant in OCTANTS {
+        cast_light(
+            origin_xy,
+            1,
+            1.0,
+            0.0,
+            radius as i32,
+            octant,
+            grid,
+            width,
+            &mut visible,
+        );
+    }
+    (0..grid.len() as u32)
+        .filter(|idx| visible[*idx as usize])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_sees_across_open_ground() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        assert!(has_line_of_sight(0, 14, &grid, 5, false));
+        assert!(has_line_of_sight(0, 14, &grid, 5, true));
+    }
+
+    #[test]
+    fn it_is_blocked_by_walls() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        assert!(!has_line_of_sight(5, 9, &grid, 5, false));
+        assert!(!has_line_of_sight(5, 9, &grid, 5, true));
+    }
+
+    #[test]
+    fn it_only_cuts_corners_with_diagonal_movement() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 0,
+            0, 1,
+        ];
+        assert!(has_line_of_sight(0, 3, &grid, 2, false));
+        assert!(!has_line_of_sight(0, 3, &grid, 2, true));
+    }
+
+    #[test]
+    fn it_returns_the_first_wall_hit() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 0, 1, 0, 1,
+            1, 1, 1, 1, 1, 1,
+        ];
+        assert_eq!(raycast(0, 5, &grid, 6, false), Some(2));
+        assert_eq!(raycast(0, 5, &grid, 6, true), Some(2));
+        assert_eq!(raycast(6, 11, &grid, 6, false), None);
+        assert_eq!(raycast(5, 0, &grid, 6, false), Some(4));
+    }
+
+    #[test]
+    fn it_sees_everything_within_radius_on_open_ground() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        assert_eq!(
+            field_of_view(12, 2, &grid, 5),
+            vec![2, 6, 7, 8, 10, 11, 12, 13, 14, 16, 17, 18, 22]
+        );
+    }
+
+    #[test]
+    fn it_does_not_see_behind_walls() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 0, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        let visible = field_of_view(11, 4, &grid, 5);
+        assert!(visible.contains(&13));
+        assert!(!visible.contains(&14));
+        assert!(visible.contains(&4));
+        assert!(visible.contains(&24));
+    }
+}
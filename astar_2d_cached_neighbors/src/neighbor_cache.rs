@@ -0,0 +1,546 @@
+use crate::IndexType;
+use std::error::Error;
+use std::fmt;
+
+// How many tiles `par_from_fn` gives each task.
+#[cfg(feature = "rayon")]
+const PARALLEL_RUN_LENGTH: usize = 4096;
+
+/// Where the cached searches read the neighbors of each tile from, either a
+/// `Vec<Vec<_>>` from `create_neighbor_idx_cache` or a
+/// `CompactNeighborIdxCache`.
+pub trait NeighborIdxCache {
+    type Index: IndexType;
+
+    fn tile_count(&self) -> usize;
+
+    fn get_neighbors(&self, idx: Self::Index) -> &[Self::Index];
+}
+
+impl<I: IndexType> NeighborIdxCache for [Vec<I>] {
+    type Index = I;
+
+    #[inline(always)]
+    fn tile_count(&self) -> usize {
+        self.len()
+    }
+
+    #[inline(always)]
+    fn get_neighbors(&self, idx: I) -> &[I] {
+        &self[idx.index()]
+    }
+}
+
+impl<I: IndexType> NeighborIdxCache for Vec<Vec<I>> {
+    type Index = I;
+
+    #[inline(always)]
+    fn tile_count(&self) -> usize {
+        self.len()
+    }
+
+    #[inline(always)]
+    fn get_neighbors(&self, idx: I) -> &[I] {
+        &self[idx.index()]
+    }
+}
+
+/// A neighbor cache in compressed sparse row form: the neighbors of every
+/// tile in one flat array, with the neighbors of tile `idx` found between
+/// `offsets[idx]` and `offsets[idx + 1]`. It takes two allocations in total
+/// rather than one per tile, and keeps neighboring tiles close in memory.
+/// The offsets are stored as `O`, so a cache holds as many neighbors as `O`
+/// can count. `u32` offsets suit most worlds, and larger ones can build their
+/// cache with `u64` offsets through `from_fn_with_offset_type`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompactNeighborIdxCache<I = u32, O = u32> {
+    offsets: Vec<O>,
+    neighbors: Vec<I>,
+}
+
+// Panics rather than wrapping once the neighbors outgrow the offset type.
+fn to_offset<O: IndexType>(neighbor_count: usize) -> O {
+    O::try_new(neighbor_count).expect("the cache has more neighbors than its offset type can count")
+}
+
+impl<I, O: IndexType> Default for CompactNeighborIdxCache<I, O> {
+    fn default() -> Self {
+        Self {
+            offsets: vec![O::new(0)],
+            neighbors: vec![],
+        }
+    }
+}
+
+impl<I: IndexType> CompactNeighborIdxCache<I> {
+    /// Builds the cache from the neighbors of each tile in turn.
+    pub fn from_fn<F: FnMut(usize) -> Vec<I>>(tile_count: usize, get_neighbors: F) -> Self {
+        Self::from_fn_with_offset_type(tile_count, get_neighbors)
+    }
+
+    /// Like `from_fn`, calling `get_neighbors` for runs of tiles across
+    /// rayon's threads. The cache built is the same as `from_fn`'s.
+    #[cfg(feature = "rayon")]
+    pub fn par_from_fn<F: Fn(usize) -> Vec<I> + Sync>(tile_count: usize, get_neighbors: F) -> Self {
+        Self::par_from_fn_with_offset_type(tile_count, get_neighbors)
+    }
+
+    pub fn from_neighbor_idx_cache(neighbor_idx_cache: &[Vec<I>]) -> Self {
+        Self::from_fn(neighbor_idx_cache.len(), |idx| {
+            neighbor_idx_cache[idx].clone()
+        })
+    }
+
+    /// Reads a cache written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CacheFormatError> {
+        Self::from_bytes_with_offset_type(bytes)
+    }
+}
+
+impl<I: IndexType, O: IndexType> CompactNeighborIdxCache<I, O> {
+    /// Like `from_fn`, for a cache with offsets of type `O`.
+    pub fn from_fn_with_offset_type<F: FnMut(usize) -> Vec<I>>(
+        tile_count: usize,
+        mut get_neighbors: F,
+    ) -> Self {
+        let mut offsets = Vec::with_capacity(tile_count + 1);
+        let mut neighbors = vec![];
+        offsets.push(O::new(0));
+        for idx in 0..tile_count {
+            neighbors.extend(get_neighbors(idx));
+            offsets.push(to_offset(neighbors.len()));
+        }
+        neighbors.shrink_to_fit();
+        Self { offsets, neighbors }
+    }
+
+    /// Like `par_from_fn`, for a cache with offsets of type `O`.
+    #[cfg(feature = "rayon")]
+    pub fn par_from_fn_with_offset_type<F: Fn(usize) -> Vec<I> + Sync>(
+        tile_count: usize,
+        get_neighbors: F,
+    ) -> Self {
+        use rayon::prelude::*;
+        // Each run collects its neighbors and where each of its tiles' neighbors
+        // end, and the runs are joined in order once they are all done.
+        let runs: Vec<(Vec<usize>, Vec<I>)> = (0..tile_count.div_ceil(PARALLEL_RUN_LENGTH))
+            .into_par_iter()
+            .map(|run| {
+                let start = run * PARALLEL_RUN_LENGTH;
+                let end = (start + PARALLEL_RUN_LENGTH).min(tile_count);
+                let mut ends = Vec::with_capacity(end - start);
+                let mut neighbors = vec![];
+                for idx in start..end {
+                    neighbors.extend(get_neighbors(idx));
+                    ends.push(neighbors.len());
+                }
+                (ends, neighbors)
+            })
+            .collect();
+        let mut offsets = Vec::with_capacity(tile_count + 1);
+        let mut neighbors = Vec::with_capacity(runs.iter().map(|(_, run)| run.len()).sum());
+        offsets.push(O::new(0));
+        for (ends, run) in runs {
+            let start = neighbors.len();
+            offsets.extend(ends.into_iter().map(|end| to_offset::<O>(start + end)));
+            neighbors.extend(run);
+        }
+        Self { offsets, neighbors }
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    #[inline(always)]
+    fn neighbor_range(&self, idx: usize) -> std::ops::Range<usize> {
+        self.offsets[idx].index()..self.offsets[idx + 1].index()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replaces the neighbors of the given tiles. When no tile's neighbor
+    /// count changes this writes over the old neighbors in place, otherwise
+    /// the flat array is rebuilt once for the whole batch.
+    pub fn replace_neighbors(&mut self, mut updates: Vec<(I, Vec<I>)>) {
+        updates.sort_by_key(|(idx, _)| *idx);
+        updates.dedup_by_key(|(idx, _)| *idx);
+        let resized = updates
+            .iter()
+            .any(|(idx, neighbors)| neighbors.len() != self.neighbor_range(idx.index()).len());
+        if !resized {
+            for (idx, neighbors) in updates {
+                let range = self.neighbor_range(idx.index());
+                self.neighbors[range].copy_from_slice(&neighbors);
+            }
+            return;
+        }
+        let mut updates = updates.into_iter().peekable();
+        let old = std::mem::take(self);
+        *self = Self::from_fn_with_offset_type(old.len(), |idx| {
+            match updates.next_if(|(next, _)| next.index() == idx) {
+                Some((_, neighbors)) => neighbors,
+                None => old.neighbors[old.neighbor_range(idx)].to_vec(),
+            }
+        });
+    }
+}
+
+const MAGIC: &[u8; 4] = b"NBRC";
+
+/// The version of the layout written by `CompactNeighborIdxCache::to_bytes`.
+pub const CACHE_FORMAT_VERSION: u16 = 1;
+
+/// Why bytes couldn't be read as a `CompactNeighborIdxCache`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CacheFormatError {
+    NotACache,
+    UnsupportedVersion(u16),
+    Truncated,
+    NeighborOutOfRange {
+        tile: u64,
+    },
+    /// The cache has more tiles than the index type can hold.
+    TooManyTiles(u64),
+    /// The cache has more neighbors in total than the offset type can count.
+    TooManyNeighbors,
+    TrailingBytes,
+}
+
+impl fmt::Display for CacheFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CacheFormatError::NotACache => write!(f, "not a neighbor cache"),
+            CacheFormatError::UnsupportedVersion(version) => write!(
+                f,
+                "cache version {} is not supported, expected {}",
+                version, CACHE_FORMAT_VERSION
+            ),
+            CacheFormatError::Truncated => write!(f, "cache ends early"),
+            CacheFormatError::NeighborOutOfRange { tile } => {
+                write!(f, "tile {} has a neighbor outside the cache", tile)
+            }
+            CacheFormatError::TooManyTiles(tile_count) => {
+                write!(f, "{} tiles is too many for the index type", tile_count)
+            }
+            CacheFormatError::TooManyNeighbors => write!(f, "cache has too many neighbors"),
+            CacheFormatError::TrailingBytes => write!(f, "unexpected bytes after the cache"),
+        }
+    }
+}
+
+impl Error for CacheFormatError {}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, CacheFormatError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(CacheFormatError::Truncated)?;
+        *bytes = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(CacheFormatError::NotACache)
+}
+
+impl<I: IndexType, O: IndexType> CompactNeighborIdxCache<I, O> {
+    /// Writes the cache in a compact binary layout that doesn't depend on the
+    /// index type, so it can be saved alongside a level rather than rebuilt on
+    /// load:
+    ///
+    /// - the 4 bytes `NBRC` followed by `CACHE_FORMAT_VERSION` as a little
+    ///   endian `u16`
+    /// - the tile count
+    /// - for each tile, its neighbor count followed by each neighbor's offset
+    ///   from the tile, zigzag encoded so that small offsets either way stay
+    ///   small
+    ///
+    /// Counts and offsets are LEB128 varints, so most neighbors take one or two
+    /// bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(CACHE_FORMAT_VERSION.to_le_bytes());
+        write_varint(&mut bytes, self.len() as u64);
+        for idx in 0..self.len() {
+            let neighbors = &self.neighbors[self.neighbor_range(idx)];
+            write_varint(&mut bytes, neighbors.len() as u64);
+            for neighbor in neighbors {
+                let offset = neighbor.index() as i64 - idx as i64;
+                write_varint(&mut bytes, (offset << 1 ^ offset >> 63) as u64);
+            }
+        }
+        bytes
+    }
+
+    /// Like `from_bytes`, for a cache with offsets of type `O`.
+    pub fn from_bytes_with_offset_type(bytes: &[u8]) -> Result<Self, CacheFormatError> {
+        let mut bytes = match bytes.strip_prefix(MAGIC) {
+            Some(bytes) => bytes,
+            None => return Err(CacheFormatError::NotACache),
+        };
+        let version = match bytes {
+            [low, high, rest @ ..] => {
+                bytes = rest;
+                u16::from_le_bytes([*low, *high])
+            }
+            _ => return Err(CacheFormatError::Truncated),
+        };
+        if version != CACHE_FORMAT_VERSION {
+            return Err(CacheFormatError::UnsupportedVersion(version));
+        }
+        let tile_count = read_varint(&mut bytes)?;
+        if tile_count > 0 && I::try_new(tile_count as usize - 1).is_none() {
+            return Err(CacheFormatError::TooManyTiles(tile_count));
+        }
+        // Every tile takes at least a byte, which stops a corrupt count from
+        // allocating a huge cache.
+        if tile_count > bytes.len() as u64 {
+            return Err(CacheFormatError::Truncated);
+        }
+        let mut offsets = Vec::with_capacity(tile_count as usize + 1);
+        let mut neighbors = vec![];
+        offsets.push(O::new(0));
+        for idx in 0..tile_count {
+            let neighbor_count = read_varint(&mut bytes)?;
+            for _ in 0..neighbor_count {
+                let offset = read_varint(&mut bytes)?;
+                let offset = (offset >> 1) as i64 ^ -((offset & 1) as i64);
+                let neighbor = (idx as i64)
+                    .checked_add(offset)
+                    .filter(|neighbor| (0..tile_count as i64).contains(neighbor))
+                    .ok_or(CacheFormatError::NeighborOutOfRange { tile: idx })?;
+                neighbors.push(I::new(neighbor as usize));
+            }
+            let end = O::try_new(neighbors.len()).ok_or(CacheFormatError::TooManyNeighbors)?;
+            offsets.push(end);
+        }
+        if !bytes.is_empty() {
+            return Err(CacheFormatError::TrailingBytes);
+        }
+        Ok(Self { offsets, neighbors })
+    }
+}
+
+// Saved with the same layout as `to_bytes`.
+#[cfg(feature = "serde")]
+impl<I: IndexType, O: IndexType> serde::Serialize for CompactNeighborIdxCache<I, O> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.to_bytes())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, I: IndexType, O: IndexType> serde::Deserialize<'de> for CompactNeighborIdxCache<I, O> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CacheVisitor<I, O>(std::marker::PhantomData<(I, O)>);
+
+        impl<'de, I: IndexType, O: IndexType> serde::de::Visitor<'de> for CacheVisitor<I, O> {
+            type Value = CompactNeighborIdxCache<I, O>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("the bytes of a neighbor cache")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+                CompactNeighborIdxCache::from_bytes_with_offset_type(bytes).map_err(E::custom)
+            }
+
+            // Formats without a bytes type, such as JSON, write bytes as a
+            // sequence of numbers.
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                self.visit_bytes(&bytes)
+            }
+        }
+
+        deserializer.deserialize_bytes(CacheVisitor(std::marker::PhantomData))
+    }
+}
+
+impl<I: IndexType, O: IndexType> NeighborIdxCache for CompactNeighborIdxCache<I, O> {
+    type Index = I;
+
+    #[inline(always)]
+    fn tile_count(&self) -> usize {
+        self.len()
+    }
+
+    #[inline(always)]
+    fn get_neighbors(&self, idx: I) -> &[I] {
+        &self.neighbors[self.neighbor_range(idx.index())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_stores_neighbors_in_one_array() {
+        let neighbor_idx_cache: Vec<Vec<u32>> = vec![vec![1, 2], vec![], vec![0]];
+        let compact = CompactNeighborIdxCache::from_neighbor_idx_cache(&neighbor_idx_cache);
+        assert_eq!(compact.len(), 3);
+        for idx in 0..3 {
+            assert_eq!(
+                compact.get_neighbors(idx),
+                neighbor_idx_cache.get_neighbors(idx)
+            );
+        }
+    }
+
+    #[test]
+    fn it_replaces_neighbors() {
+        let neighbor_idx_cache: Vec<Vec<u16>> = vec![vec![1, 2], vec![0], vec![0]];
+        let mut compact = CompactNeighborIdxCache::from_neighbor_idx_cache(&neighbor_idx_cache);
+        compact.replace_neighbors(vec![(1, vec![2])]);
+        assert_eq!(compact.get_neighbors(1), &[2]);
+        compact.replace_neighbors(vec![(2, vec![]), (0, vec![1])]);
+        assert_eq!(compact.get_neighbors(0), &[1]);
+        assert_eq!(compact.get_neighbors(1), &[2]);
+        assert_eq!(compact.get_neighbors(2), &[] as &[u16]);
+    }
+
+    #[test]
+    fn it_counts_neighbors_with_the_offset_type() {
+        // 70000 neighbors in all, more than u16 offsets can count.
+        let get_neighbors = |idx: usize| vec![idx as u64; 7];
+        let compact =
+            CompactNeighborIdxCache::<u64, u32>::from_fn_with_offset_type(10000, get_neighbors);
+        assert_eq!(compact.get_neighbors(9999), [9999; 7]);
+        let bytes = compact.to_bytes();
+        assert_eq!(
+            CompactNeighborIdxCache::<u64, u16>::from_bytes_with_offset_type(&bytes),
+            Err(CacheFormatError::TooManyNeighbors)
+        );
+        assert_eq!(
+            CompactNeighborIdxCache::<u64, u32>::from_bytes_with_offset_type(&bytes),
+            Ok(compact)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "more neighbors than its offset type can count")]
+    fn it_panics_when_the_offsets_overflow() {
+        CompactNeighborIdxCache::<u64, u16>::from_fn_with_offset_type(10000, |idx| {
+            vec![idx as u64; 7]
+        });
+    }
+
+    #[test]
+    #[ignore = "needs about 35 GB of memory"]
+    fn it_holds_more_neighbors_than_u32_offsets_can_count() {
+        let neighbor_count = u32::MAX as usize;
+        let compact =
+            CompactNeighborIdxCache::<u64, u64>::from_fn_with_offset_type(2, |idx| match idx {
+                0 => vec![1; neighbor_count],
+                _ => vec![0; 2],
+            });
+        assert_eq!(compact.get_neighbors(0).len(), neighbor_count);
+        assert_eq!(compact.get_neighbors(1), [0, 0]);
+    }
+
+    #[test]
+    fn it_saves_caches_as_bytes() {
+        let neighbor_idx_cache: Vec<Vec<u16>> = vec![vec![1, 3], vec![0, 2], vec![1], vec![0]];
+        let compact = CompactNeighborIdxCache::from_neighbor_idx_cache(&neighbor_idx_cache);
+        let bytes = compact.to_bytes();
+        assert_eq!(
+            bytes,
+            [b'N', b'B', b'R', b'C', 1, 0, 4, 2, 2, 6, 2, 1, 2, 1, 1, 1, 5]
+        );
+        assert_eq!(
+            CompactNeighborIdxCache::from_bytes(&bytes),
+            Ok(compact.clone())
+        );
+        // The layout doesn't depend on the index type.
+        let wide = CompactNeighborIdxCache::<u64>::from_bytes(&bytes).unwrap();
+        assert_eq!(wide.to_bytes(), bytes);
+        assert_eq!(wide.get_neighbors(0), [1, 3]);
+    }
+
+    #[test]
+    fn it_rejects_malformed_cache_bytes() {
+        let bytes =
+            CompactNeighborIdxCache::from_neighbor_idx_cache(&[vec![1u32], vec![0]]).to_bytes();
+        let read = |bytes: &[u8]| CompactNeighborIdxCache::<u32>::from_bytes(bytes);
+        assert_eq!(read(b"NBR"), Err(CacheFormatError::NotACache));
+        assert_eq!(
+            read(&[b"NBRC".as_slice(), &[2, 0, 0]].concat()),
+            Err(CacheFormatError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            read(&bytes[..bytes.len() - 1]),
+            Err(CacheFormatError::Truncated)
+        );
+        assert_eq!(
+            read(&[&bytes[..], &[0]].concat()),
+            Err(CacheFormatError::TrailingBytes)
+        );
+        // Tile 1 pointing two tiles on.
+        let mut out_of_range = bytes.clone();
+        *out_of_range.last_mut().unwrap() = 4;
+        assert_eq!(
+            read(&out_of_range),
+            Err(CacheFormatError::NeighborOutOfRange { tile: 1 })
+        );
+        let mut huge = b"NBRC\x01\x00".to_vec();
+        write_varint(&mut huge, 1 << 16 | 1);
+        huge.extend(vec![0; 1 << 17]);
+        assert_eq!(
+            CompactNeighborIdxCache::<u16>::from_bytes(&huge),
+            Err(CacheFormatError::TooManyTiles(1 << 16 | 1))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_a_level_with_its_cache() {
+        use crate::{create_compact_neighbor_idx_cache, find_path};
+
+        let grid = vec![1; 9];
+        let cache: CompactNeighborIdxCache = create_compact_neighbor_idx_cache(&grid, 3);
+        let path = find_path(0, 8, &grid, 3, &cache);
+        type Level = (Vec<u32>, u32, CompactNeighborIdxCache, Vec<u32>);
+        let level: Level = (grid, 3, cache, path);
+        let bytes = bincode::serialize(&level).unwrap();
+        assert_eq!(bincode::deserialize::<Level>(&bytes).unwrap(), level);
+        // The cache is written with its own layout, after the length bincode
+        // gives every byte string.
+        let cache_bytes = bincode::serialize(&level.2).unwrap();
+        assert_eq!(&cache_bytes[8..], level.2.to_bytes());
+        assert!(bincode::deserialize::<CompactNeighborIdxCache>(&cache_bytes[..20]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_rejects_serialized_caches_with_bad_offsets() {
+        let mut bytes =
+            CompactNeighborIdxCache::from_neighbor_idx_cache(&[vec![1u32], vec![0]]).to_bytes();
+        // Tile 1 pointing two tiles on.
+        *bytes.last_mut().unwrap() = 4;
+        let error =
+            bincode::deserialize::<CompactNeighborIdxCache>(&bincode::serialize(&bytes).unwrap())
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            CacheFormatError::NeighborOutOfRange { tile: 1 }.to_string()
+        );
+    }
+}
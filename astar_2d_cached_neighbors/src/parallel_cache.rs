@@ -0,0 +1,118 @@
+use crate::{get_cardinal_neighbor_idxs, get_neighbor_idxs, CompactNeighborIdxCache, IndexType};
+use rayon::prelude::*;
+
+/// Like `create_neighbor_idx_cache`, looking up the neighbors of each tile
+/// across rayon's threads. The cache built is the same as the serial one.
+pub fn par_create_neighbor_idx_cache(grid: &[u32], width: u32) -> Vec<Vec<u32>> {
+    par_create_neighbor_idx_cache_with_index_type(grid, width)
+}
+
+/// Like `par_create_neighbor_idx_cache`, with the indices stored as `I`.
+pub fn par_create_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+) -> Vec<Vec<I>> {
+    (0..grid.len())
+        .into_par_iter()
+        .map(|idx| match grid[idx] {
+            0 => vec![],
+            _ => get_neighbor_idxs(idx, grid, width),
+        })
+        .collect()
+}
+
+/// Like `create_cardinal_neighbor_idx_cache`, looking up the neighbors of
+/// each tile across rayon's threads.
+pub fn par_create_cardinal_neighbor_idx_cache(grid: &[u32], width: u32) -> Vec<Vec<u32>> {
+    par_create_cardinal_neighbor_idx_cache_with_index_type(grid, width)
+}
+
+/// Like `par_create_cardinal_neighbor_idx_cache`, with the indices stored as
+/// `I`.
+pub fn par_create_cardinal_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+) -> Vec<Vec<I>> {
+    (0..grid.len())
+        .into_par_iter()
+        .map(|idx| match grid[idx] {
+            0 => vec![],
+            _ => get_cardinal_neighbor_idxs(idx, grid, width),
+        })
+        .collect()
+}
+
+/// Like `create_compact_neighbor_idx_cache`, looking up the neighbors of each
+/// tile across rayon's threads.
+pub fn par_create_compact_neighbor_idx_cache(grid: &[u32], width: u32) -> CompactNeighborIdxCache {
+    par_create_compact_neighbor_idx_cache_with_index_type(grid, width)
+}
+
+/// Like `par_create_compact_neighbor_idx_cache`, with the indices stored as
+/// `I`.
+pub fn par_create_compact_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::par_from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => get_neighbor_idxs(idx, grid, width),
+    })
+}
+
+/// Like `create_compact_cardinal_neighbor_idx_cache`, looking up the
+/// neighbors of each tile across rayon's threads.
+pub fn par_create_compact_cardinal_neighbor_idx_cache(
+    grid: &[u32],
+    width: u32,
+) -> CompactNeighborIdxCache {
+    par_create_compact_cardinal_neighbor_idx_cache_with_index_type(grid, width)
+}
+
+/// Like `par_create_compact_cardinal_neighbor_idx_cache`, with the indices
+/// stored as `I`.
+pub fn par_create_compact_cardinal_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    width: u32,
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::par_from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => get_cardinal_neighbor_idxs(idx, grid, width),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        create_cardinal_neighbor_idx_cache, create_compact_cardinal_neighbor_idx_cache,
+        create_compact_neighbor_idx_cache, create_neighbor_idx_cache,
+    };
+
+    #[test]
+    fn it_builds_the_same_caches_in_parallel() {
+        // Enough tiles for several runs, with the last one cut short.
+        let width = 100;
+        let grid: Vec<u32> = (0..width * 123).map(|idx| (idx % 7 != 3) as u32).collect();
+        assert_eq!(
+            par_create_neighbor_idx_cache(&grid, width),
+            create_neighbor_idx_cache(&grid, width)
+        );
+        assert_eq!(
+            par_create_cardinal_neighbor_idx_cache(&grid, width),
+            create_cardinal_neighbor_idx_cache(&grid, width)
+        );
+        assert_eq!(
+            par_create_compact_neighbor_idx_cache(&grid, width).to_bytes(),
+            create_compact_neighbor_idx_cache(&grid, width).to_bytes()
+        );
+        assert_eq!(
+            par_create_compact_cardinal_neighbor_idx_cache(&grid, width).to_bytes(),
+            create_compact_cardinal_neighbor_idx_cache(&grid, width).to_bytes()
+        );
+        assert_eq!(
+            par_create_compact_neighbor_idx_cache(&[], width),
+            CompactNeighborIdxCache::default()
+        );
+    }
+}
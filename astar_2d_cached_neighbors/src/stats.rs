@@ -0,0 +1,285 @@
+use crate::{
+    search_with_buffers, IndexType, NeighborIdxCache, SearchBuffers, SearchObserver, SearchOptions,
+};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How much work a search did.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStats {
+    /// Tiles whose neighbors were looked at.
+    pub expansions: u64,
+    /// Times a cheaper way to an already queued tile was found.
+    pub relaxations: u64,
+    /// The most tiles waiting to be expanded at once.
+    pub peak_frontier: usize,
+    /// Queue entries skipped because their tile had already been expanded.
+    /// Without the skip, each would have been expanded again.
+    pub stale_entries: u64,
+    pub time: Duration,
+}
+
+/// Counts how much work a search does as it runs. Pass it to
+/// `find_path_with_observer` through `measure`, which also times the search.
+// A relaxed tile is queued again but still only waits once.
+#[derive(Default)]
+pub struct StatsObserver {
+    stats: SearchStats,
+    frontier: usize,
+}
+
+impl<C, I> SearchObserver<C, I> for StatsObserver {
+    #[inline(always)]
+    fn on_push(&mut self, _idx: I, _cost: C) {
+        self.frontier += 1;
+        self.stats.peak_frontier = self.stats.peak_frontier.max(self.frontier);
+    }
+
+    #[inline(always)]
+    fn on_expand(&mut self, _idx: I, _cost: C) {
+        self.stats.expansions += 1;
+        self.frontier -= 1;
+    }
+
+    #[inline(always)]
+    fn on_relax(&mut self, _idx: I, _old_cost: C, _new_cost: C) {
+        self.stats.relaxations += 1;
+        self.frontier -= 1;
+    }
+
+    #[inline(always)]
+    fn on_skip(&mut self, _idx: I) {
+        self.stats.stale_entries += 1;
+    }
+}
+
+/// Runs a search with a `StatsObserver`, timing it from start to finish.
+pub fn measure<P>(search: impl FnOnce(&mut StatsObserver) -> P) -> (P, SearchStats) {
+    let started = Instant::now();
+    let mut observer = StatsObserver::default();
+    let result = search(&mut observer);
+    observer.stats.time = started.elapsed();
+    (result, observer.stats)
+}
+
+/// Adds up the stats of every search run through it. Searches run through
+/// `find_path` reuse the context's buffers, so keep one context per thread
+/// rather than one per search.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "", deserialize = "I: Ord"))
+)]
+pub struct SearchContext<I = u32> {
+    pub searches: u64,
+    pub paths_found: u64,
+    /// The sum of every search's stats, except for `peak_frontier`, which is
+    /// the largest of any one search.
+    pub totals: SearchStats,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    buffers: SearchBuffers<u32, I>,
+}
+
+impl<I: Ord> Default for SearchContext<I> {
+    fn default() -> Self {
+        Self {
+            searches: 0,
+            paths_found: 0,
+            totals: SearchStats::default(),
+            buffers: SearchBuffers::default(),
+        }
+    }
+}
+
+// The buffers only hold what the last search left behind, so they are left
+// out of clones, comparisons and debug output.
+impl<I: Ord> Clone for SearchContext<I> {
+    fn clone(&self) -> Self {
+        Self {
+            searches: self.searches,
+            paths_found: self.paths_found,
+            totals: self.totals,
+            buffers: SearchBuffers::default(),
+        }
+    }
+}
+
+impl<I> PartialEq for SearchContext<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.searches == other.searches
+            && self.paths_found == other.paths_found
+            && self.totals == other.totals
+    }
+}
+
+impl<I> Eq for SearchContext<I> {}
+
+impl<I> fmt::Debug for SearchContext<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SearchContext")
+            .field("searches", &self.searches)
+            .field("paths_found", &self.paths_found)
+            .field("totals", &self.totals)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<I: IndexType> SearchContext<I> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a search that was run some other way.
+    pub fn record(&mut self, stats: &SearchStats, found: bool) {
+        self.searches += 1;
+        self.paths_found += found as u64;
+        self.totals.expansions += stats.expansions;
+        self.totals.relaxations += stats.relaxations;
+        self.totals.stale_entries += stats.stale_entries;
+        self.totals.peak_frontier = self.totals.peak_frontier.max(stats.peak_frontier);
+        self.totals.time += stats.time;
+    }
+
+    /// Adds another context's totals to this one, such as one kept by another
+    /// thread.
+    pub fn merge(&mut self, other: &Self) {
+        self.searches += other.searches;
+        self.paths_found += other.paths_found;
+        self.totals.expansions += other.totals.expansions;
+        self.totals.relaxations += other.totals.relaxations;
+        self.totals.stale_entries += other.totals.stale_entries;
+        self.totals.peak_frontier = self.totals.peak_frontier.max(other.totals.peak_frontier);
+        self.totals.time += other.totals.time;
+    }
+
+    /// Like `find_path`, measuring the search and adding its stats to the totals.
+    pub fn find_path<N: NeighborIdxCache<Index = I> + ?Sized>(
+        &mut self,
+        start: N::Index,
+        end: N::Index,
+        grid: &[u32],
+        width: u32,
+        neighbors: &N,
+    ) -> (Vec<N::Index>, SearchStats) {
+        let (path, stats) = measure(|observer| {
+            search_with_buffers(
+                start,
+                end,
+                width,
+                neighbors,
+                |_, idx: N::Index| Some(grid[idx.index()]),
+                &SearchOptions::default(),
+                observer,
+                &mut self.buffers,
+            )
+        });
+        self.record(&stats, !path.is_empty());
+        (path, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_neighbor_idx_cache, find_path, find_path_with_observer};
+
+    #[test]
+    fn it_counts_search_work() {
+        #[rustfmt::skip]
+        let grid = [
+            1, 1, 1, 1, 1,
+            1, 1, 1, 9, 1,
+            9, 9, 9, 9, 1,
+            1, 1, 1, 1, 1,
+            9, 1, 1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, 5);
+        let (path, stats) =
+            measure(|observer| find_path_with_observer(0, 24, &grid, 5, &neighbors, observer));
+        assert_eq!(path, find_path(0, 24, &grid, 5, &neighbors));
+        assert_eq!(
+            stats,
+            SearchStats {
+                expansions: 11,
+                relaxations: 3,
+                peak_frontier: 8,
+                stale_entries: 2,
+                time: stats.time,
+            }
+        );
+    }
+
+    #[test]
+    fn it_adds_up_stats_across_searches() {
+        #[rustfmt::skip]
+        let grid = [
+            1, 1, 1,
+            0, 0, 1,
+            1, 0, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, 3);
+        let mut context = SearchContext::new();
+        let (_, found) = context.find_path(0, 8, &grid, 3, &neighbors);
+        let (path, not_found) = context.find_path(0, 6, &grid, 3, &neighbors);
+        assert!(path.is_empty());
+        // Every reachable tile is expanded before giving up.
+        assert_eq!(not_found.expansions, 5);
+        assert_eq!(context.searches, 2);
+        assert_eq!(context.paths_found, 1);
+        assert_eq!(
+            context.totals.expansions,
+            found.expansions + not_found.expansions
+        );
+        assert_eq!(
+            context.totals.peak_frontier,
+            found.peak_frontier.max(not_found.peak_frontier)
+        );
+        assert_eq!(context.totals.time, found.time + not_found.time);
+        let mut merged = context.clone();
+        merged.merge(&context);
+        assert_eq!(merged.searches, 4);
+        assert_eq!(merged.totals.expansions, context.totals.expansions * 2);
+        assert_eq!(merged.totals.peak_frontier, context.totals.peak_frontier);
+    }
+
+    #[test]
+    fn it_counts_a_search_from_the_end_as_found() {
+        let grid = [1; 9];
+        let neighbors = create_neighbor_idx_cache(&grid, 3);
+        let mut context = SearchContext::new();
+        let (path, stats) = context.find_path(4, 4, &grid, 3, &neighbors);
+        assert_eq!(path, vec![4]);
+        assert_eq!(stats.expansions, 0);
+        assert_eq!(context.paths_found, 1);
+    }
+
+    #[test]
+    fn it_reuses_its_buffers_between_grids() {
+        #[rustfmt::skip]
+        let large = [
+            1, 1, 1, 1, 1,
+            1, 1, 1, 9, 1,
+            9, 9, 9, 9, 1,
+            1, 1, 1, 1, 1,
+            9, 1, 1, 1, 1,
+        ];
+        let small = [1; 9];
+        let large_neighbors = create_neighbor_idx_cache(&large, 5);
+        let small_neighbors = create_neighbor_idx_cache(&small, 3);
+        let mut context = SearchContext::new();
+        let (path, first) = context.find_path(0, 24, &large, 5, &large_neighbors);
+        assert_eq!(path, find_path(0, 24, &large, 5, &large_neighbors));
+        let (path, _) = context.find_path(0, 8, &small, 3, &small_neighbors);
+        assert_eq!(path, find_path(0, 8, &small, 3, &small_neighbors));
+        let (path, again) = context.find_path(0, 24, &large, 5, &large_neighbors);
+        assert_eq!(path, find_path(0, 24, &large, 5, &large_neighbors));
+        assert_eq!(
+            again,
+            SearchStats {
+                time: again.time,
+                ..first
+            }
+        );
+    }
+}
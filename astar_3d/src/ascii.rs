@@ -0,0 +1,342 @@
+//! A text format for writing maps by hand, and for drawing the paths found on
+//! them.
+//!
+//! Each character is a tile: `.` is floor, `#` is a wall and the digits `1`
+//! to `9` are tiles with that cost. `<` is floor with stairs up to the tile
+//! above it on the next floor, and `>` is floor with stairs down. `S` and `E`
+//! are floor tiles marking where a path starts and ends. Floors are separated
+//! by blank lines, and leading whitespace is ignored so maps can be indented
+//! to match the code around them.
+//!
+//! ```text
+//! S..
+//! .#<
+//!
+//! ..>
+//! E..
+//! ```
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    WrongRowWidth {
+        floor: u32,
+        row: u32,
+        expected: u32,
+        found: u32,
+    },
+    WrongRowCount {
+        floor: u32,
+        expected: u32,
+        found: u32,
+    },
+    UnknownTile {
+        floor: u32,
+        row: u32,
+        column: u32,
+        tile: char,
+    },
+    DuplicateMarker(char),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "the map has no tiles"),
+            ParseError::WrongRowWidth {
+                floor,
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} of floor {} is {} tiles wide rather than {}",
+                row, floor, found, expected
+            ),
+            ParseError::WrongRowCount {
+                floor,
+                expected,
+                found,
+            } => write!(
+                f,
+                "floor {} has {} rows rather than {}",
+                floor, found, expected
+            ),
+            ParseError::UnknownTile {
+                floor,
+                row,
+                column,
+                tile,
+            } => write!(
+                f,
+                "unknown tile `{}` at row {} column {} of floor {}",
+                tile, row, column, floor
+            ),
+            ParseError::DuplicateMarker(marker) => write!(f, "`{}` appears more than once", marker),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// A parsed map, ready to pass to `find_path` and the other searches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AsciiMap {
+    pub grid: Vec<u32>,
+    pub dimensions: (u32, u32),
+    pub up_stairs_idxs: HashSet<u32>,
+    pub down_stairs_idxs: HashSet<u32>,
+    pub start: Option<u32>,
+    pub end: Option<u32>,
+}
+
+impl FromStr for AsciiMap {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = text.lines().map(str::trim).collect();
+        let floors: Vec<&[&str]> = lines
+            .split(|line| line.is_empty())
+            .filter(|floor| !floor.is_empty())
+            .collect();
+        let (width, height) = match floors.first() {
+            Some(rows) => (rows[0].chars().count() as u32, rows.len() as u32),
+            None => return Err(ParseError::Empty),
+        };
+        let mut map = AsciiMap {
+            grid: Vec::with_capacity((width * height) as usize * floors.len()),
+            dimensions: (width, height),
+            up_stairs_idxs: HashSet::new(),
+            down_stairs_idxs: HashSet::new(),
+            start: None,
+            end: None,
+        };
+        for (floor, rows) in floors.iter().enumerate() {
+            let floor = floor as u32;
+            if rows.len() as u32 != height {
+                return Err(ParseError::WrongRowCount {
+                    floor,
+                    expected: height,
+                    found: rows.len() as u32,
+                });
+            }
+            for (row, line) in rows.iter().enumerate() {
+                let row = row as u32;
+                let found = line.chars().count() as u32;
+                if found != width {
+                    return Err(ParseError::WrongRowWidth {
+                        floor,
+                        row,
+                        expected: width,
+                        found,
+                    });
+                }
+                for (column, tile) in line.chars().enumerate() {
+                    let idx = map.grid.len() as u32;
+                    let cost = match tile {
+                        '.' => 1,
+                        '#' => 0,
+                        '1'..='9' => tile.to_digit(10).unwrap(),
+                        '<' => {
+                            map.up_stairs_idxs.insert(idx);
+                            1
+                        }
+                        '>' => {
+                            map.down_stairs_idxs.insert(idx);
+                            1
+                        }
+                        'S' | 'E' => {
+                            let marker = match tile {
+                                'S' => &mut map.start,
+                                _ => &mut map.end,
+                            };
+                            if marker.replace(idx).is_some() {
+                                return Err(ParseError::DuplicateMarker(tile));
+                            }
+                            1
+                        }
+                        _ => {
+                            return Err(ParseError::UnknownTile {
+                                floor,
+                                row,
+                                column: column as u32,
+                                tile,
+                            })
+                        }
+                    };
+                    map.grid.push(cost);
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl AsciiMap {
+    /// Draws the map back in the format it is parsed from, with `start`
+    /// marked `S`, the last tile of `path` marked `E` and the rest of `path`
+    /// marked `*`. Costs above 9 are drawn as `+`.
+    pub fn render_path(&self, start: u32, path: &[u32]) -> String {
+        let mut marks = vec![None; self.grid.len()];
+        for &idx in path {
+            marks[idx as usize] = Some('*');
+        }
+        if let Some(&end) = path.last() {
+            marks[end as usize] = Some('E');
+        }
+        marks[start as usize] = Some('S');
+        self.render(|idx| marks[idx as usize])
+    }
+
+    /// Like `render_path`, with `mark` choosing a character to draw over any
+    /// tile instead.
+    pub fn render<F: Fn(u32) -> Option<char>>(&self, mark: F) -> String {
+        let (width, height) = self.dimensions;
+        let tile_count = width as usize * height as usize;
+        let tiles: Vec<char> = self
+            .grid
+            .iter()
+            .enumerate()
+            .map(|(idx, &cost)| {
+                let idx = idx as u32;
+                mark(idx).unwrap_or(match cost {
+                    0 => '#',
+                    _ if self.up_stairs_idxs.contains(&idx) => '<',
+                    _ if self.down_stairs_idxs.contains(&idx) => '>',
+                    1 => '.',
+                    2..=9 => char::from_digit(cost, 10).unwrap(),
+                    _ => '+',
+                })
+            })
+            .collect();
+        tiles
+            .chunks(tile_count)
+            .map(|floor| {
+                floor
+                    .chunks(width as usize)
+                    .map(|row| row.iter().collect::<String>() + "\n")
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    #[test]
+    fn it_parses_floors_and_stairs() {
+        let map: AsciiMap = "
+            S..
+            .2<
+
+            ..>
+            E.#
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(map.grid, vec![1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 0]);
+        assert_eq!(map.dimensions, (3, 2));
+        assert_eq!(map.up_stairs_idxs, HashSet::from([5]));
+        assert_eq!(map.down_stairs_idxs, HashSet::from([8]));
+        assert_eq!(map.start, Some(0));
+        assert_eq!(map.end, Some(9));
+        // Stepping onto the stairs at 5 comes out above them at 11, the only
+        // way up, and the stairs at 8 are the only way back down to 2.
+        let (start, end) = (map.start.unwrap(), map.end.unwrap());
+        let find = |from, to| {
+            find_path(
+                from,
+                to,
+                &map.grid,
+                map.dimensions,
+                &map.up_stairs_idxs,
+                &map.down_stairs_idxs,
+            )
+        };
+        let up = find(start, end);
+        assert_eq!(up.last(), Some(&end));
+        assert!(up.contains(&11));
+        let down = find(end, start);
+        assert_eq!(down.last(), Some(&start));
+        assert!(down.contains(&2));
+    }
+
+    #[test]
+    fn it_rejects_malformed_maps() {
+        assert_eq!("\n \n".parse::<AsciiMap>(), Err(ParseError::Empty));
+        assert_eq!(
+            "...\n..\n".parse::<AsciiMap>(),
+            Err(ParseError::WrongRowWidth {
+                floor: 0,
+                row: 1,
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            "..\n..\n\n..\n".parse::<AsciiMap>(),
+            Err(ParseError::WrongRowCount {
+                floor: 1,
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            ".?\n".parse::<AsciiMap>(),
+            Err(ParseError::UnknownTile {
+                floor: 0,
+                row: 0,
+                column: 1,
+                tile: '?'
+            })
+        );
+        assert_eq!(
+            "S.S\n".parse::<AsciiMap>(),
+            Err(ParseError::DuplicateMarker('S'))
+        );
+    }
+
+    #[test]
+    fn it_renders_paths() {
+        let map: AsciiMap = "
+            S.#.
+            .#..
+            ..#<
+
+            ....
+            .##.
+            E..>
+        "
+        .parse()
+        .unwrap();
+        let (start, end) = (map.start.unwrap(), map.end.unwrap());
+        let path = find_path(
+            start,
+            end,
+            &map.grid,
+            map.dimensions,
+            &map.up_stairs_idxs,
+            &map.down_stairs_idxs,
+        );
+        assert_eq!(
+            map.render_path(start, &path),
+            "S*#.\n.#*.\n..#<\n\n....\n.##.\nE***\n"
+        );
+        assert_eq!(
+            map.render(|idx| (idx == 5).then_some('*')),
+            "..#.\n.*..\n..#<\n\n....\n.##.\n...>\n"
+        );
+        let unmarked = AsciiMap { end: None, ..map };
+        assert_eq!(unmarked.render_path(start, &[]).parse(), Ok(unmarked));
+    }
+}
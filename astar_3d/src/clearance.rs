@@ -0,0 +1,121 @@
+use crate::find_path_with_edge_cost;
+use std::collections::HashSet;
+
+// True clearance: the size of the largest open square on the same floor whose
+// top left corner is `idx`, capped at `max_clearance`. Relies on the tiles to
+// the right of and below `idx` already being filled in.
+#[inline(always)]
+fn get_clearance(
+    idx: u32,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    max_clearance: u32,
+    clearance: &[u32],
+) -> u32 {
+    if grid[idx as usize] == 0 {
+        return 0;
+    }
+    let (width, height) = dimensions;
+    let tile_count = width as usize * height as usize;
+    let is_right = idx % width == width - 1;
+    let is_bottom = idx as usize % tile_count >= tile_count - width as usize;
+    if is_right || is_bottom {
+        return max_clearance.min(1);
+    }
+    let right = clearance[idx as usize + 1];
+    let bottom = clearance[(idx + width) as usize];
+    let bottom_right = clearance[(idx + width) as usize + 1];
+    max_clearance.min(right.min(bottom).min(bottom_right) + 1)
+}
+
+/// Builds a clearance map for `find_path_with_clearance`. Each tile holds the
+/// size of the largest square agent that fits on its floor with its top left
+/// corner on that tile, or 0 for walls. Values are capped at `max_clearance`,
+/// which only needs to be as large as the biggest agent.
+pub fn create_clearance_map(grid: &[u32], dimensions: (u32, u32), max_clearance: u32) -> Vec<u32> {
+    let mut clearance = vec![0; grid.len()];
+    for idx in (0..grid.len() as u32).rev() {
+        clearance[idx as usize] = get_clearance(idx, grid, dimensions, max_clearance, &clearance);
+    }
+    clearance
+}
+
+/// Like `find_path`, but for a square agent `agent_size` tiles across whose
+/// top left corner is the tile being pathed. Only tiles with at least that
+/// much clearance are used.
+#[allow(clippy::too_many_arguments)]
+pub fn find_path_with_clearance(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+    clearance: &[u32],
+    agent_size: u32,
+) -> Vec<u32> {
+    find_path_with_edge_cost(
+        start,
+        end,
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+        |_, idx| (clearance[idx as usize] >= agent_size).then(|| grid[idx as usize]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_creates_a_clearance_map_per_floor() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1,
+        ];
+        #[rustfmt::skip]
+        let expected = vec![
+            3, 2, 1,
+            2, 2, 1,
+            1, 1, 1,
+
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1,
+        ];
+        assert_eq!(create_clearance_map(&grid, (3, 3), 3), expected);
+    }
+
+    #[test]
+    fn it_keeps_large_agents_out_of_narrow_gaps() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            0, 0, 1, 0, 0, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let dimensions = (7, 5);
+        let no_stairs = HashSet::new();
+        let clearance = create_clearance_map(&grid, dimensions, 2);
+        let path = find_path_with_clearance(
+            0, 21, &grid, dimensions, &no_stairs, &no_stairs, &clearance, 1,
+        );
+        assert!(path.contains(&16));
+        let path = find_path_with_clearance(
+            0, 21, &grid, dimensions, &no_stairs, &no_stairs, &clearance, 2,
+        );
+        assert!(path.contains(&19));
+        assert!(path.iter().all(|&idx| clearance[idx as usize] >= 2));
+        assert_eq!(path.last(), Some(&21));
+    }
+}
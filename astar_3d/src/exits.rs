@@ -0,0 +1,138 @@
+use crate::{find_path_with_edge_cost, get_coords};
+use std::collections::HashSet;
+
+pub const NORTH: u16 = 1;
+pub const NORTH_EAST: u16 = 1 << 1;
+pub const EAST: u16 = 1 << 2;
+pub const SOUTH_EAST: u16 = 1 << 3;
+pub const SOUTH: u16 = 1 << 4;
+pub const SOUTH_WEST: u16 = 1 << 5;
+pub const WEST: u16 = 1 << 6;
+pub const NORTH_WEST: u16 = 1 << 7;
+pub const UP: u16 = 1 << 8;
+pub const DOWN: u16 = 1 << 9;
+pub const ALL_EXITS: u16 = (1 << 10) - 1;
+
+/// The exit bits for stepping from `from` to the adjacent tile `to`. Taking
+/// stairs combines the direction of the stairs with `UP` or `DOWN`.
+#[inline(always)]
+pub fn get_exit_direction(from: u32, to: u32, dimensions: (u32, u32)) -> u16 {
+    let (from_x, from_y, from_floor) = get_coords(from, dimensions);
+    let (to_x, to_y, to_floor) = get_coords(to, dimensions);
+    let dx = to_x as i32 - from_x as i32;
+    let dy = to_y as i32 - from_y as i32;
+    let depth = to_floor as i32 - from_floor as i32;
+    let horizontal = match (dx.signum(), dy.signum()) {
+        (0, -1) => NORTH,
+        (1, -1) => NORTH_EAST,
+        (1, 0) => EAST,
+        (1, 1) => SOUTH_EAST,
+        (0, 1) => SOUTH,
+        (-1, 1) => SOUTH_WEST,
+        (-1, 0) => WEST,
+        (-1, -1) => NORTH_WEST,
+        _ => 0,
+    };
+    let vertical = match depth.signum() {
+        1 => UP,
+        -1 => DOWN,
+        _ => 0,
+    };
+    horizontal | vertical
+}
+
+/// Like `find_path`, but a tile can only be left in the directions set in its
+/// `exits` bitmask, which allows one-way doors, conveyors and ledges.
+#[allow(clippy::too_many_arguments)]
+pub fn find_path_with_exits(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+    exits: &[u16],
+) -> Vec<u32> {
+    find_path_with_edge_cost(
+        start,
+        end,
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+        |from, to| {
+            let direction = get_exit_direction(from, to, dimensions);
+            (exits[from as usize] & direction == direction).then(|| grid[to as usize])
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    #[test]
+    fn it_gets_exit_directions() {
+        assert_eq!(get_exit_direction(4, 1, (3, 3)), NORTH);
+        assert_eq!(get_exit_direction(4, 8, (3, 3)), SOUTH_EAST);
+        assert_eq!(get_exit_direction(4, 3, (3, 3)), WEST);
+        assert_eq!(get_exit_direction(4, 14, (3, 3)), EAST | UP);
+        assert_eq!(get_exit_direction(13, 3, (3, 3)), WEST | DOWN);
+        // A floor of 65536 by 65536 tiles has more tiles than a u32 can count.
+        assert_eq!(get_exit_direction(0, 65537, (65536, 65536)), SOUTH_EAST);
+    }
+
+    #[test]
+    fn it_only_leaves_tiles_through_their_exits() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+
+            1, 1, 1,
+        ];
+        let dimensions = (3, 1);
+        let up_stairs_idxs = HashSet::from([1]);
+        let down_stairs_idxs = HashSet::from([4]);
+        let all_exits = vec![ALL_EXITS; grid.len()];
+        assert_eq!(
+            find_path_with_exits(
+                0,
+                5,
+                &grid,
+                dimensions,
+                &up_stairs_idxs,
+                &down_stairs_idxs,
+                &all_exits
+            ),
+            find_path(0, 5, &grid, dimensions, &up_stairs_idxs, &down_stairs_idxs)
+        );
+        // A ledge: the upper floor can be dropped down from but not climbed.
+        #[rustfmt::skip]
+        let exits = vec![
+            ALL_EXITS & !UP, ALL_EXITS & !UP, ALL_EXITS & !UP,
+
+            ALL_EXITS, ALL_EXITS, ALL_EXITS,
+        ];
+        let path = find_path_with_exits(
+            0,
+            5,
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+            &exits,
+        );
+        assert_eq!(path, vec![]);
+        let path = find_path_with_exits(
+            3,
+            2,
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+            &exits,
+        );
+        assert_eq!(path, vec![1, 2]);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{find_path_with_observer, SearchObserver};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// How much work a search did.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStats {
+    /// Tiles whose neighbors were looked at.
+    pub expansions: u64,
+    /// Times a cheaper way to an already queued tile was found.
+    pub relaxations: u64,
+    /// The most tiles waiting to be expanded at once.
+    pub peak_frontier: usize,
+    /// Queue entries skipped because their tile had already been expanded.
+    /// Without the skip, each would have been expanded again.
+    pub stale_entries: u64,
+    pub time: Duration,
+}
+
+/// Counts how much work a search does as it runs. Pass it to
+/// `find_path_with_observer` through `measure`, which also times the search.
+// A relaxed tile is queued again but still only waits once.
+#[derive(Default)]
+pub struct StatsObserver {
+    stats: SearchStats,
+    frontier: usize,
+}
+
+impl<C> SearchObserver<C> for StatsObserver {
+    #[inline(always)]
+    fn on_push(&mut self, _idx: u32, _cost: C) {
+        self.frontier += 1;
+        self.stats.peak_frontier = self.stats.peak_frontier.max(self.frontier);
+    }
+
+    #[inline(always)]
+    fn on_expand(&mut self, _idx: u32, _cost: C) {
+        self.stats.expansions += 1;
+        self.frontier -= 1;
+    }
+
+    #[inline(always)]
+    fn on_relax(&mut self, _idx: u32, _old_cost: C, _new_cost: C) {
+        self.stats.relaxations += 1;
+        self.frontier -= 1;
+    }
+
+    #[inline(always)]
+    fn on_skip(&mut self, _idx: u32) {
+        self.stats.stale_entries += 1;
+    }
+}
+
+/// Runs a search with a `StatsObserver`, timing it from start to finish.
+pub fn measure<P>(search: impl FnOnce(&mut StatsObserver) -> P) -> (P, SearchStats) {
+    let started = Instant::now();
+    let mut observer = StatsObserver::default();
+    let result = search(&mut observer);
+    observer.stats.time = started.elapsed();
+    (result, observer.stats)
+}
+
+/// Adds up the stats of every search run through it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchContext {
+    pub searches: u64,
+    pub paths_found: u64,
+    /// The sum of every search's stats, except for `peak_frontier`, which is
+    /// the largest of any one search.
+    pub totals: SearchStats,
+}
+
+impl SearchContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a search that was run some other way.
+    pub fn record(&mut self, stats: &SearchStats, found: bool) {
+        self.searches += 1;
+        self.paths_found += found as u64;
+        self.totals.expansions += stats.expansions;
+        self.totals.relaxations += stats.relaxations;
+        self.totals.stale_entries += stats.stale_entries;
+        self.totals.peak_frontier = self.totals.peak_frontier.max(stats.peak_frontier);
+        self.totals.time += stats.time;
+    }
+
+    /// Adds another context's totals to this one, such as one kept by another
+    /// thread.
+    pub fn merge(&mut self, other: &SearchContext) {
+        self.searches += other.searches;
+        self.paths_found += other.paths_found;
+        self.totals.expansions += other.totals.expansions;
+        self.totals.relaxations += other.totals.relaxations;
+        self.totals.stale_entries += other.totals.stale_entries;
+        self.totals.peak_frontier = self.totals.peak_frontier.max(other.totals.peak_frontier);
+        self.totals.time += other.totals.time;
+    }
+
+    /// Like `find_path`, measuring the search and adding its stats to the totals.
+    pub fn find_path(
+        &mut self,
+        start: u32,
+        end: u32,
+        grid: &[u32],
+        dimensions: (u32, u32),
+        up_stairs_idxs: &HashSet<u32>,
+        down_stairs_idxs: &HashSet<u32>,
+    ) -> (Vec<u32>, SearchStats) {
+        let (path, stats) = measure(|observer| {
+            find_path_with_observer(
+                start,
+                end,
+                grid,
+                dimensions,
+                up_stairs_idxs,
+                down_stairs_idxs,
+                observer,
+            )
+        });
+        self.record(&stats, !path.is_empty());
+        (path, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_path;
+
+    #[test]
+    fn it_counts_search_work() {
+        #[rustfmt::skip]
+        let grid = [
+            1, 1, 1, 1, 1,
+            1, 1, 1, 9, 1,
+            9, 9, 9, 9, 1,
+            1, 1, 1, 1, 1,
+            9, 1, 1, 1, 1,
+        ];
+        let no_stairs = HashSet::new();
+        let (path, stats) = measure(|observer| {
+            find_path_with_observer(0, 24, &grid, (5, 5), &no_stairs, &no_stairs, observer)
+        });
+        assert_eq!(
+            path,
+            find_path(0, 24, &grid, (5, 5), &no_stairs, &no_stairs)
+        );
+        assert_eq!(
+            stats,
+            SearchStats {
+                expansions: 11,
+                relaxations: 3,
+                peak_frontier: 8,
+                stale_entries: 2,
+                time: stats.time,
+            }
+        );
+    }
+
+    #[test]
+    fn it_adds_up_stats_across_searches() {
+        #[rustfmt::skip]
+        let grid = [
+            1, 1, 1,
+            0, 0, 1,
+            1, 0, 1,
+
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let up_stairs_idxs = HashSet::from([8]);
+        let no_stairs = HashSet::new();
+        let mut context = SearchContext::new();
+        let (_, found) = context.find_path(0, 9, &grid, (3, 3), &up_stairs_idxs, &no_stairs);
+        let (path, not_found) = context.find_path(0, 6, &grid, (3, 3), &up_stairs_idxs, &no_stairs);
+        assert!(path.is_empty());
+        // Every reachable tile is expanded before giving up.
+        assert_eq!(not_found.expansions, 14);
+        assert_eq!(context.searches, 2);
+        assert_eq!(context.paths_found, 1);
+        assert_eq!(
+            context.totals.expansions,
+            found.expansions + not_found.expansions
+        );
+        assert_eq!(
+            context.totals.peak_frontier,
+            found.peak_frontier.max(not_found.peak_frontier)
+        );
+        assert_eq!(context.totals.time, found.time + not_found.time);
+        let mut merged = context.clone();
+        merged.merge(&context);
+        assert_eq!(merged.searches, 4);
+        assert_eq!(merged.totals.expansions, context.totals.expansions * 2);
+        assert_eq!(merged.totals.peak_frontier, context.totals.peak_frontier);
+    }
+
+    #[test]
+    fn it_counts_a_search_from_the_end_as_found() {
+        let no_stairs = HashSet::new();
+        let mut context = SearchContext::new();
+        let (path, stats) = context.find_path(4, 4, &[1; 9], (3, 3), &no_stairs, &no_stairs);
+        assert_eq!(path, vec![4]);
+        assert_eq!(stats.expansions, 0);
+        assert_eq!(context.paths_found, 1);
+    }
+}
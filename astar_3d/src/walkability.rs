@@ -0,0 +1,294 @@
+use crate::{search_with_neighbors, SearchOptions};
+use smallvec::{smallvec, SmallVec};
+use std::collections::HashSet;
+
+/// One bit per tile saying whether it can be entered, packed into words a row
+/// at a time with the rows of every floor stacked one after another.
+/// Neighbors are read three at a time from each row rather than tile by tile
+/// from the cost grid, and runs of open tiles can be measured a word at a
+/// time. Rows are padded to whole words with walls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WalkabilityGrid {
+    dimensions: (u32, u32),
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+impl WalkabilityGrid {
+    /// Marks every tile with a cost above 0 as walkable.
+    pub fn new(grid: &[u32], dimensions: (u32, u32)) -> Self {
+        let width = dimensions.0;
+        let row_count = grid.len() / width as usize;
+        let words_per_row = (width as usize).div_ceil(64);
+        let mut walkability = Self {
+            dimensions,
+            words_per_row,
+            bits: vec![0; words_per_row * row_count],
+        };
+        for (idx, &cost) in grid.iter().enumerate() {
+            walkability.set_walkable(idx as u32, cost > 0);
+        }
+        walkability
+    }
+
+    #[inline(always)]
+    fn locate(&self, idx: u32) -> (usize, u32) {
+        let width = self.dimensions.0;
+        let row = (idx / width) as usize;
+        let x = idx % width;
+        (row * self.words_per_row + x as usize / 64, x % 64)
+    }
+
+    #[inline(always)]
+    pub fn is_walkable(&self, idx: u32) -> bool {
+        let (word, bit) = self.locate(idx);
+        self.bits[word] >> bit & 1 == 1
+    }
+
+    pub fn set_walkable(&mut self, idx: u32, walkable: bool) {
+        let (word, bit) = self.locate(idx);
+        match walkable {
+            true => self.bits[word] |= 1 << bit,
+            _ => self.bits[word] &= !(1 << bit),
+        }
+    }
+
+    /// Brings the given tiles back in sync with `grid` after it has changed.
+    pub fn update(&mut self, grid: &[u32], update_idxs: &[u32]) {
+        for &idx in update_idxs {
+            self.set_walkable(idx, grid[idx as usize] > 0);
+        }
+    }
+
+    // The 64 tiles of `row` starting at `x`, with tiles past the end of the
+    // row read as walls. Rows are counted across floors.
+    #[inline(always)]
+    fn read_row(&self, row: u32, x: u32) -> u64 {
+        let words = &self.bits[row as usize * self.words_per_row..][..self.words_per_row];
+        let (word, bit) = (x as usize / 64, x % 64);
+        let low = words.get(word).map_or(0, |word| word >> bit);
+        let high = match bit {
+            0 => 0,
+            _ => words.get(word + 1).map_or(0, |word| word << (64 - bit)),
+        };
+        low | high
+    }
+
+    // Tiles `x - 1`, `x` and `x + 1` of `row` in the lowest three bits.
+    #[inline(always)]
+    fn read_window(&self, row: u32, x: u32) -> u64 {
+        match x {
+            0 => self.read_row(row, 0) << 1 & 0b111,
+            _ => self.read_row(row, x - 1) & 0b111,
+        }
+    }
+
+    /// How many walkable tiles there are in a row east of `idx`, stopping at
+    /// the first wall or the edge of the floor.
+    pub fn count_walkable_east(&self, idx: u32) -> u32 {
+        let width = self.dimensions.0;
+        let (row, mut x) = (idx / width, idx % width + 1);
+        let mut count = 0;
+        while x < width {
+            let run = self.read_row(row, x).trailing_ones();
+            count += run;
+            if run < 64 {
+                break;
+            }
+            x += 64;
+        }
+        count.min(width - idx % width - 1)
+    }
+
+    /// How many walkable tiles there are in a row west of `idx`, stopping at
+    /// the first wall or the edge of the floor.
+    pub fn count_walkable_west(&self, idx: u32) -> u32 {
+        let width = self.dimensions.0;
+        let (row, x) = (idx / width, idx % width);
+        let mut count = 0;
+        let mut end = x;
+        while end > 0 {
+            let start = end.saturating_sub(64);
+            let length = end - start;
+            // The tiles from `start` up to `end`, nearest first in the highest
+            // bit.
+            let tiles = self.read_row(row, start) << (64 - length);
+            let run = tiles.leading_ones().min(length);
+            count += run;
+            if run < length {
+                break;
+            }
+            end = start;
+        }
+        count
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_neighbor_coords(
+        &self,
+        current: u32,
+        up_stairs_idxs: &HashSet<u32>,
+        down_stairs_idxs: &HashSet<u32>,
+    ) -> SmallVec<[u32; 24]> {
+        let (width, height) = self.dimensions;
+        let tile_count = width as usize * height as usize;
+        let (x, row) = (current % width, current / width);
+        let y = row % height;
+        let above = match y {
+            0 => 0,
+            _ => self.read_window(row - 1, x),
+        };
+        let middle = self.read_window(row, x);
+        let below = match y + 1 < height {
+            true => self.read_window(row + 1, x),
+            _ => 0,
+        };
+        let mut neighbors: SmallVec<[u32; 24]> = smallvec![];
+        if above & 0b010 != 0 {
+            neighbors.push(current - width);
+        }
+        if above & 0b001 != 0 {
+            neighbors.push(current - width - 1);
+        }
+        if above & 0b100 != 0 {
+            neighbors.push(current - width + 1);
+        }
+        if middle & 0b001 != 0 {
+            neighbors.push(current - 1);
+        }
+        if middle & 0b100 != 0 {
+            neighbors.push(current + 1);
+        }
+        if below & 0b010 != 0 {
+            neighbors.push(current + width);
+        }
+        if below & 0b001 != 0 {
+            neighbors.push(current + width - 1);
+        }
+        if below & 0b100 != 0 {
+            neighbors.push(current + width + 1);
+        }
+        let mut vertical_neighbors: SmallVec<[u32; 16]> = smallvec![];
+        for neighbor in &neighbors {
+            if up_stairs_idxs.contains(neighbor) {
+                vertical_neighbors.push((*neighbor as usize + tile_count) as u32)
+            }
+            if down_stairs_idxs.contains(neighbor) {
+                vertical_neighbors.push((*neighbor as usize - tile_count) as u32)
+            }
+        }
+        neighbors.append(&mut vertical_neighbors);
+        neighbors
+    }
+}
+
+/// Like `find_path`, but walls come from `walkability` rather than from 0s in
+/// `grid`, which only supplies costs. This lets tiles with a cost of 0 be
+/// walked on for free.
+pub fn find_path_with_walkability(
+    start: u32,
+    end: u32,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+    walkability: &WalkabilityGrid,
+) -> Vec<u32> {
+    search_with_neighbors(
+        start,
+        end,
+        grid.len(),
+        dimensions,
+        |idx| walkability.get_neighbor_coords(idx, up_stairs_idxs, down_stairs_idxs),
+        |_, idx| Some(grid[idx as usize]),
+        &SearchOptions::default(),
+        (),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_path, get_neighbor_coords};
+
+    #[test]
+    fn it_packs_walkability_into_bits() {
+        let grid: Vec<u32> = (0..400).map(|idx| (idx % 7 != 3) as u32).collect();
+        let mut walkability = WalkabilityGrid::new(&grid, (100, 2));
+        for idx in 0..400 {
+            assert_eq!(walkability.is_walkable(idx), grid[idx as usize] > 0);
+        }
+        walkability.set_walkable(206, true);
+        assert!(walkability.is_walkable(206));
+        walkability.update(&grid, &[206]);
+        assert!(!walkability.is_walkable(206));
+    }
+
+    #[test]
+    fn it_gets_the_same_neighbors_as_the_grid() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1,
+            1, 0, 1, 0,
+            1, 1, 0, 1,
+
+            0, 1, 1, 1,
+            1, 1, 0, 1,
+            1, 0, 1, 1,
+        ];
+        let up_stairs_idxs = HashSet::from([5, 6]);
+        let down_stairs_idxs = HashSet::from([17]);
+        let walkability = WalkabilityGrid::new(&grid, (4, 3));
+        for idx in 0..grid.len() as u32 {
+            assert_eq!(
+                walkability.get_neighbor_coords(idx, &up_stairs_idxs, &down_stairs_idxs),
+                get_neighbor_coords(idx, &grid, (4, 3), &up_stairs_idxs, &down_stairs_idxs)
+            );
+        }
+    }
+
+    #[test]
+    fn it_counts_walkable_runs_across_words() {
+        let mut grid = vec![1; 300];
+        grid[10] = 0;
+        grid[140] = 0;
+        let walkability = WalkabilityGrid::new(&grid, (150, 1));
+        assert_eq!(walkability.count_walkable_east(0), 9);
+        assert_eq!(walkability.count_walkable_east(11), 128);
+        assert_eq!(walkability.count_walkable_east(149), 0);
+        assert_eq!(walkability.count_walkable_east(150), 149);
+        assert_eq!(walkability.count_walkable_west(139), 128);
+        assert_eq!(walkability.count_walkable_west(150), 0);
+        assert_eq!(walkability.count_walkable_west(299), 149);
+    }
+
+    #[test]
+    fn it_paths_with_walkability() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1,
+
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let up_stairs_idxs = HashSet::from([8]);
+        let down_stairs_idxs = HashSet::from([17]);
+        let walkability = WalkabilityGrid::new(&grid, (3, 3));
+        assert_eq!(
+            find_path_with_walkability(
+                0,
+                9,
+                &grid,
+                (3, 3),
+                &up_stairs_idxs,
+                &down_stairs_idxs,
+                &walkability
+            ),
+            find_path(0, 9, &grid, (3, 3), &up_stairs_idxs, &down_stairs_idxs)
+        );
+    }
+}
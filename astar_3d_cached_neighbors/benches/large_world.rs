@@ -0,0 +1,89 @@
+//! Times building neighbor caches for the largest multi-floor maps, at about
+//! 16 million tiles, serially and across rayon's threads. Each cache takes
+//! seconds to build, far too long for `#[bench]`'s hundreds of runs, so every
+//! builder is timed over a handful of runs instead. Run with
+//! `cargo bench --features rayon`.
+use astar_3d_cached_neighbors::{
+    create_compact_neighbor_idx_cache, create_neighbor_idx_cache,
+    par_create_compact_neighbor_idx_cache, par_create_neighbor_idx_cache,
+};
+use hashbrown::HashSet;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const DIMENSIONS: (u32, u32) = (1024, 1024);
+const FLOORS: u32 = 16;
+const RUNS: usize = 5;
+
+// Scattered walls on every floor, and a staircase every 97 tiles between each
+// floor and the one above it.
+fn create_world() -> (Vec<u32>, HashSet<u32>, HashSet<u32>) {
+    let tile_count = DIMENSIONS.0 * DIMENSIONS.1;
+    let grid: Vec<u32> = (0..tile_count * FLOORS)
+        .map(|idx| match idx % 7 {
+            3 if idx % 97 != 0 => 0,
+            cost => cost % 3 + 1,
+        })
+        .collect();
+    let up_stairs_idxs: HashSet<u32> = (0..tile_count * (FLOORS - 1)).step_by(97).collect();
+    let down_stairs_idxs = up_stairs_idxs.iter().map(|idx| idx + tile_count).collect();
+    (grid, up_stairs_idxs, down_stairs_idxs)
+}
+
+// The median time of `RUNS` runs of `build`.
+fn time<T>(name: &str, mut build: impl FnMut() -> T) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            black_box(build());
+            started.elapsed()
+        })
+        .collect();
+    times.sort();
+    let median = times[RUNS / 2];
+    println!("{name:<48} {median:>12.2?}");
+    median
+}
+
+fn main() {
+    let (grid, up_stairs_idxs, down_stairs_idxs) = create_world();
+    println!(
+        "{}x{}x{} tiles, median of {} runs, rayon threads: {}",
+        DIMENSIONS.0,
+        DIMENSIONS.1,
+        FLOORS,
+        RUNS,
+        rayon::current_num_threads()
+    );
+    let serial = time("create_neighbor_idx_cache", || {
+        create_neighbor_idx_cache(&grid, DIMENSIONS, &up_stairs_idxs, &down_stairs_idxs)
+    });
+    let parallel = time("par_create_neighbor_idx_cache", || {
+        par_create_neighbor_idx_cache(&grid, DIMENSIONS, &up_stairs_idxs, &down_stairs_idxs)
+    });
+    println!(
+        "{:<48} {:>11.2}x",
+        "speedup",
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+    let serial = time("create_compact_neighbor_idx_cache", || {
+        create_compact_neighbor_idx_cache(&grid, DIMENSIONS, &up_stairs_idxs, &down_stairs_idxs)
+    });
+    let parallel = time("par_create_compact_neighbor_idx_cache", || {
+        par_create_compact_neighbor_idx_cache(&grid, DIMENSIONS, &up_stairs_idxs, &down_stairs_idxs)
+    });
+    println!(
+        "{:<48} {:>11.2}x",
+        "speedup",
+        serial.as_secs_f64() / parallel.as_secs_f64()
+    );
+    let compact =
+        create_compact_neighbor_idx_cache(&grid, DIMENSIONS, &up_stairs_idxs, &down_stairs_idxs);
+    let par_compact = par_create_compact_neighbor_idx_cache(
+        &grid,
+        DIMENSIONS,
+        &up_stairs_idxs,
+        &down_stairs_idxs,
+    );
+    assert!(compact.to_bytes() == par_compact.to_bytes());
+}
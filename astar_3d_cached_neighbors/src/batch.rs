@@ -0,0 +1,95 @@
+use crate::{IndexType, NeighborIdxCache, SearchContext, SearchStats};
+use rayon::prelude::*;
+
+/// Finds a path for every `(start, end)` pair in `queries`, spread across
+/// rayon's threads. Paths are returned in the same order as `queries`.
+pub fn find_paths<N: NeighborIdxCache + Sync + ?Sized>(
+    queries: &[(N::Index, N::Index)],
+    grid: &[u32],
+    dimensions: (u32, u32),
+    neighbors: &N,
+) -> Vec<Vec<N::Index>> {
+    SearchContext::new()
+        .find_paths(queries, grid, dimensions, neighbors)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect()
+}
+
+impl<I: IndexType + Send> SearchContext<I> {
+    /// `find_paths`, also returning the stats of each search and adding them
+    /// to the totals. Each of rayon's threads keeps one context for all of its
+    /// searches, reusing its buffers, and their totals are added to this one.
+    pub fn find_paths<N: NeighborIdxCache<Index = I> + Sync + ?Sized>(
+        &mut self,
+        queries: &[(I, I)],
+        grid: &[u32],
+        dimensions: (u32, u32),
+        neighbors: &N,
+    ) -> Vec<(Vec<I>, SearchStats)> {
+        let results: Vec<(Vec<I>, SearchStats)> = queries
+            .par_iter()
+            .map_init(SearchContext::new, |context, &(start, end)| {
+                context.find_path(start, end, grid, dimensions, neighbors)
+            })
+            .collect();
+        for (path, stats) in &results {
+            self.record(stats, !path.is_empty());
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_compact_neighbor_idx_cache, create_neighbor_idx_cache, find_path};
+    use hashbrown::HashSet;
+
+    #[test]
+    fn it_finds_paths_in_parallel() {
+        let dimensions = (16, 16);
+        let grid: Vec<u32> = (0..16 * 16 * 3)
+            .map(|idx| (idx % 7 != 3 || idx % 16 == 0) as u32)
+            .collect();
+        let up_stairs_idxs: HashSet<u32> = [16, 256 + 16].into();
+        let down_stairs_idxs: HashSet<u32> = [256 + 32, 512 + 32].into();
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &up_stairs_idxs, &down_stairs_idxs);
+        let queries: Vec<(u32, u32)> = (0..200)
+            .map(|i| {
+                (
+                    (i * 37) % grid.len() as u32,
+                    (i * 101 + 5) % grid.len() as u32,
+                )
+            })
+            .collect();
+        let expected: Vec<Vec<u32>> = queries
+            .iter()
+            .map(|&(start, end)| find_path(start, end, &grid, dimensions, &neighbors))
+            .collect();
+        assert_eq!(
+            find_paths(&queries, &grid, dimensions, &neighbors),
+            expected
+        );
+        let compact = create_compact_neighbor_idx_cache(
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+        );
+        let mut context = SearchContext::new();
+        let results = context.find_paths(&queries, &grid, dimensions, &compact);
+        assert_eq!(context.searches, 200);
+        assert_eq!(
+            context.totals.expansions,
+            results
+                .iter()
+                .map(|(_, stats)| stats.expansions)
+                .sum::<u64>()
+        );
+        for ((path, _), expected) in results.iter().zip(&expected) {
+            assert_eq!(path, expected);
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{get_neighbor_idxs_with_index_type, CompactNeighborIdxCache, IndexType};
+use hashbrown::HashSet;
+
+pub const NORTH: u16 = 1;
+pub const NORTH_EAST: u16 = 1 << 1;
+pub const EAST: u16 = 1 << 2;
+pub const SOUTH_EAST: u16 = 1 << 3;
+pub const SOUTH: u16 = 1 << 4;
+pub const SOUTH_WEST: u16 = 1 << 5;
+pub const WEST: u16 = 1 << 6;
+pub const NORTH_WEST: u16 = 1 << 7;
+pub const UP: u16 = 1 << 8;
+pub const DOWN: u16 = 1 << 9;
+pub const ALL_EXITS: u16 = (1 << 10) - 1;
+
+/// The exit bits for stepping from `from` to the adjacent tile `to`. Taking
+/// stairs combines the direction of the stairs with `UP` or `DOWN`.
+#[inline(always)]
+pub fn get_exit_direction(from: u32, to: u32, dimensions: (u32, u32)) -> u16 {
+    get_exit_bits(from as usize, to as usize, dimensions)
+}
+
+// Works on usize so caches with any index type can use it.
+#[inline(always)]
+fn get_exit_bits(from: usize, to: usize, dimensions: (u32, u32)) -> u16 {
+    let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
+    let tile_count = width * height;
+    let dx = (to % width) as i64 - (from % width) as i64;
+    let dy = (to % tile_count / width) as i64 - (from % tile_count / width) as i64;
+    let depth = (to / tile_count) as i64 - (from / tile_count) as i64;
+    let horizontal = match (dx.signum(), dy.signum()) {
+        (0, -1) => NORTH,
+        (1, -1) => NORTH_EAST,
+        (1, 0) => EAST,
+        (1, 1) => SOUTH_EAST,
+        (0, 1) => SOUTH,
+        (-1, 1) => SOUTH_WEST,
+        (-1, 0) => WEST,
+        (-1, -1) => NORTH_WEST,
+        _ => 0,
+    };
+    let vertical = match depth.signum() {
+        1 => UP,
+        -1 => DOWN,
+        _ => 0,
+    };
+    horizontal | vertical
+}
+
+/// Like `get_neighbor_idxs`, but only the neighbors reachable through the
+/// exits set in `exits[current]`.
+pub fn get_neighbor_idxs_with_exits(
+    current: u32,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+    exits: &[u16],
+) -> Vec<u32> {
+    get_neighbor_idxs_with_exits_and_index_type(
+        current,
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+        exits,
+    )
+}
+
+/// Like `get_neighbor_idxs_with_exits`, with the indices stored as `I`.
+pub fn get_neighbor_idxs_with_exits_and_index_type<I: IndexType>(
+    current: I,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+    exits: &[u16],
+) -> Vec<I> {
+    let mut neighbors = get_neighbor_idxs_with_index_type(
+        current,
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+    );
+    neighbors.retain(|&neighbor| {
+        let direction = get_exit_bits(current.index(), neighbor.index(), dimensions);
+        exits[current.index()] & direction == direction
+    });
+    neighbors
+}
+
+/// Like `create_neighbor_idx_cache`, but a tile can only be left in the
+/// directions set in its `exits` bitmask, which allows one-way doors,
+/// conveyors and ledges.
+pub fn create_neighbor_idx_cache_with_exits(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+    exits: &[u16],
+) -> Vec<Vec<u32>> {
+    create_neighbor_idx_cache_with_exits_and_index_type(
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+        exits,
+    )
+}
+
+/// Like `create_neighbor_idx_cache_with_exits`, with the indices stored as
+/// `I`.
+pub fn create_neighbor_idx_cache_with_exits_and_index_type<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+    exits: &[u16],
+) -> Vec<Vec<I>> {
+    let mut neighbor_idx_cache = vec![];
+    for idx in 0..grid.len() {
+        let neighbors = if grid[idx] == 0 {
+            vec![]
+        } else {
+            get_neighbor_idxs_with_exits_and_index_type(
+                I::new(idx),
+                grid,
+                dimensions,
+                up_stairs_idxs,
+                down_stairs_idxs,
+                exits,
+            )
+        };
+        neighbor_idx_cache.push(neighbors);
+    }
+    neighbor_idx_cache
+}
+
+/// Like `create_neighbor_idx_cache_with_exits`, but builds a
+/// `CompactNeighborIdxCache`.
+pub fn create_compact_neighbor_idx_cache_with_exits(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+    exits: &[u16],
+) -> CompactNeighborIdxCache {
+    create_compact_neighbor_idx_cache_with_exits_and_index_type(
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+        exits,
+    )
+}
+
+/// Like `create_compact_neighbor_idx_cache_with_exits`, with the indices
+/// stored as `I`.
+pub fn create_compact_neighbor_idx_cache_with_exits_and_index_type<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+    exits: &[u16],
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => get_neighbor_idxs_with_exits_and_index_type(
+            I::new(idx),
+            grid,
+            dimensions,
+            up_stairs_idxs,
+            down_stairs_idxs,
+            exits,
+        ),
+    })
+}
+
+/// Like `update_neighbor_idx_cache` for a cache built with
+/// `create_neighbor_idx_cache_with_exits`. The tiles in `update_idxs` are
+/// refreshed as well as their neighbors, since their own exits may have
+/// changed.
+pub fn update_neighbor_idx_cache_with_exits(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+    exits: &[u16],
+    neighbors: &mut [Vec<u32>],
+    update_idxs: &[u32],
+) {
+    update_neighbor_idx_cache_with_exits_and_index_type(
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+        exits,
+        neighbors,
+        update_idxs,
+    )
+}
+
+/// Like `update_neighbor_idx_cache_with_exits`, for a cache with the indices
+/// stored as `I`.
+pub fn update_neighbor_idx_cache_with_exits_and_index_type<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+    exits: &[u16],
+    neighbors: &mut [Vec<I>],
+    update_idxs: &[I],
+) {
+    for &idx in update_idxs {
+        let mut affected_idxs = get_neighbor_idxs_with_index_type(
+            idx,
+            grid,
+            dimensions,
+            up_stairs_idxs,
+            down_stairs_idxs,
+        );
+        affected_idxs.push(idx);
+        for affected_idx in affected_idxs {
+            neighbors[affected_idx.index()] = if grid[affected_idx.index()] == 0 {
+                vec![]
+            } else {
+                get_neighbor_idxs_with_exits_and_index_type(
+                    affected_idx,
+                    grid,
+                    dimensions,
+                    up_stairs_idxs,
+                    down_stairs_idxs,
+                    exits,
+                )
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_neighbor_idx_cache, find_path, NeighborIdxCache};
+
+    #[test]
+    fn it_gets_exit_directions() {
+        assert_eq!(get_exit_direction(4, 1, (3, 3)), NORTH);
+        assert_eq!(get_exit_direction(4, 8, (3, 3)), SOUTH_EAST);
+        assert_eq!(get_exit_direction(4, 3, (3, 3)), WEST);
+        assert_eq!(get_exit_direction(4, 14, (3, 3)), EAST | UP);
+        assert_eq!(get_exit_direction(13, 3, (3, 3)), WEST | DOWN);
+    }
+
+    #[test]
+    fn it_only_leaves_tiles_through_their_exits() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+
+            1, 1, 1,
+        ];
+        let dimensions = (3, 1);
+        let up_stairs_idxs = HashSet::from([1]);
+        let down_stairs_idxs = HashSet::from([4]);
+        let all_exits = vec![ALL_EXITS; grid.len()];
+        assert_eq!(
+            create_neighbor_idx_cache_with_exits(
+                &grid,
+                dimensions,
+                &up_stairs_idxs,
+                &down_stairs_idxs,
+                &all_exits
+            ),
+            create_neighbor_idx_cache(&grid, dimensions, &up_stairs_idxs, &down_stairs_idxs)
+        );
+        // A ledge: the upper floor can be dropped down from but not climbed.
+        #[rustfmt::skip]
+        let mut exits = vec![
+            ALL_EXITS & !UP, ALL_EXITS & !UP, ALL_EXITS & !UP,
+
+            ALL_EXITS, ALL_EXITS, ALL_EXITS,
+        ];
+        let mut neighbors = create_neighbor_idx_cache_with_exits(
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+            &exits,
+        );
+        assert_eq!(find_path(0, 5, &grid, dimensions, &neighbors), vec![]);
+        assert_eq!(find_path(3, 2, &grid, dimensions, &neighbors), vec![1, 2]);
+        exits[0] = ALL_EXITS;
+        update_neighbor_idx_cache_with_exits(
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+            &exits,
+            &mut neighbors,
+            &[0],
+        );
+        assert_eq!(find_path(0, 5, &grid, dimensions, &neighbors), vec![4, 5]);
+    }
+
+    #[test]
+    fn it_builds_exit_caches_with_other_index_types() {
+        let grid = vec![1; 6];
+        let dimensions = (3, 1);
+        let mut exits = vec![ALL_EXITS & !UP; grid.len()];
+        exits[3..].fill(ALL_EXITS);
+        let mut neighbors = create_neighbor_idx_cache_with_exits_and_index_type::<u16>(
+            &grid,
+            dimensions,
+            &HashSet::from([1]),
+            &HashSet::from([4]),
+            &exits,
+        );
+        assert_eq!(neighbors[0], vec![1]);
+        exits[0] = ALL_EXITS;
+        update_neighbor_idx_cache_with_exits_and_index_type(
+            &grid,
+            dimensions,
+            &HashSet::from([1]),
+            &HashSet::from([4]),
+            &exits,
+            &mut neighbors,
+            &[0],
+        );
+        assert_eq!(neighbors[0], vec![1, 4]);
+        let neighbors = create_neighbor_idx_cache_with_exits_and_index_type::<u64>(
+            &grid,
+            dimensions,
+            &HashSet::from([1]),
+            &HashSet::from([4]),
+            &exits,
+        );
+        assert_eq!(find_path(0, 5, &grid, dimensions, &neighbors), vec![4, 5]);
+    }
+    #[test]
+    fn it_builds_compact_exit_caches() {
+        let grid = vec![1; 6];
+        let dimensions = (3, 1);
+        let up_stairs_idxs = HashSet::from([1]);
+        let down_stairs_idxs = HashSet::from([4]);
+        let mut exits = vec![ALL_EXITS & !UP; grid.len()];
+        exits[3..].fill(ALL_EXITS);
+        let compact = create_compact_neighbor_idx_cache_with_exits(
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+            &exits,
+        );
+        assert_eq!(
+            compact,
+            CompactNeighborIdxCache::from_neighbor_idx_cache(
+                &create_neighbor_idx_cache_with_exits(
+                    &grid,
+                    dimensions,
+                    &up_stairs_idxs,
+                    &down_stairs_idxs,
+                    &exits,
+                )
+            )
+        );
+        assert_eq!(find_path(0, 5, &grid, dimensions, &compact), vec![]);
+        exits[0] = ALL_EXITS;
+        let compact = create_compact_neighbor_idx_cache_with_exits_and_index_type::<u16>(
+            &grid,
+            dimensions,
+            &HashSet::from([1]),
+            &HashSet::from([4]),
+            &exits,
+        );
+        assert_eq!(compact.get_neighbors(0), [1, 4]);
+    }
+}
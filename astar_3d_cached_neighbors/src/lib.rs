@@ -1,86 +1,178 @@
 #![feature(test)]
 extern crate test;
 
+#[cfg(feature = "rayon")]
+mod batch;
+mod clearance;
+mod cost;
+pub mod exits;
+mod index;
+mod line_of_sight;
+mod neighbor_cache;
+mod observer;
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel_cache;
+mod smoothing;
+mod stats;
+mod terrain;
+
+#[cfg(feature = "rayon")]
+pub use batch::find_paths;
+pub use clearance::{create_clearance_map, find_path_with_clearance, update_clearance_map};
+pub use cost::{Cost, Fixed, OrderedFloat, OverflowPolicy};
+pub use exits::{
+    create_compact_neighbor_idx_cache_with_exits,
+    create_compact_neighbor_idx_cache_with_exits_and_index_type,
+    create_neighbor_idx_cache_with_exits, create_neighbor_idx_cache_with_exits_and_index_type,
+    get_exit_direction, get_neighbor_idxs_with_exits, get_neighbor_idxs_with_exits_and_index_type,
+    update_neighbor_idx_cache_with_exits, update_neighbor_idx_cache_with_exits_and_index_type,
+};
+pub use index::IndexType;
+pub use line_of_sight::{field_of_view, has_line_of_sight, raycast};
+pub use neighbor_cache::{
+    CacheFormatError, CompactNeighborIdxCache, NeighborIdxCache, CACHE_FORMAT_VERSION,
+};
+pub use observer::{find_path_with_observer, SearchObserver};
+pub use options::{SearchOptions, TieBreaking};
+#[cfg(feature = "rayon")]
+pub use parallel_cache::{
+    par_create_compact_neighbor_idx_cache, par_create_compact_neighbor_idx_cache_with_index_type,
+    par_create_neighbor_idx_cache, par_create_neighbor_idx_cache_with_index_type,
+};
+pub use smoothing::{remove_collinear_points, smooth_path};
+pub use stats::{measure, SearchContext, SearchStats, StatsObserver};
+pub use terrain::{find_path_for_agent, AgentProfile};
+
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 
 use hashbrown::HashSet;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct FrontierItem {
-    pub position: u32,
-    pub cost: u32,
+struct FrontierItem<C = u32, I = u32> {
+    pub position: I,
+    pub cost: C,
+    pub tie_breaker: u64,
 }
 
-impl Ord for FrontierItem {
+impl<C: Ord, I: Ord> Ord for FrontierItem<C, I> {
     fn cmp(&self, other: &Self) -> Ordering {
         other
             .cost
             .cmp(&self.cost)
+            .then_with(|| other.tie_breaker.cmp(&self.tie_breaker))
             .then_with(|| self.position.cmp(&other.position))
     }
 }
 
-impl PartialOrd for FrontierItem {
+impl<C: Ord, I: Ord> PartialOrd for FrontierItem<C, I> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
+// What a search works in. A `SearchContext` keeps its buffers between searches
+// so that running many of them allocates once rather than for every search.
+pub(crate) struct SearchBuffers<C, I> {
+    frontier: BinaryHeap<FrontierItem<C, I>>,
+    cost_so_far: Vec<Option<C>>,
+    came_from: Vec<I>,
+    closed: Vec<bool>,
+}
+
+impl<C: Ord, I: Ord> Default for SearchBuffers<C, I> {
+    fn default() -> Self {
+        Self {
+            frontier: BinaryHeap::new(),
+            cost_so_far: vec![],
+            came_from: vec![],
+            closed: vec![],
+        }
+    }
+}
+
+impl<C: Cost, I: IndexType> SearchBuffers<C, I> {
+    // Clears whatever the last search left behind, sized for `tile_count` tiles.
+    fn reset(&mut self, tile_count: usize, start: I) {
+        self.frontier.clear();
+        self.frontier.reserve(tile_count);
+        self.cost_so_far.clear();
+        self.cost_so_far.resize(tile_count, None);
+        self.came_from.clear();
+        self.came_from.resize(tile_count, start);
+        self.closed.clear();
+        self.closed.resize(tile_count, false);
+    }
+}
+
 #[inline(always)]
 pub fn get_neighbor_idxs(
     current: u32,
-    grid: &Vec<u32>,
+    grid: &[u32],
     dimensions: (u32, u32),
     up_stairs_idxs: &HashSet<u32>,
     down_stairs_idxs: &HashSet<u32>,
 ) -> Vec<u32> {
-    let (width, height) = dimensions;
+    get_neighbor_idxs_with_index_type(current, grid, dimensions, up_stairs_idxs, down_stairs_idxs)
+}
+
+/// Like `get_neighbor_idxs`, with the indices stored as `I`.
+#[inline(always)]
+pub fn get_neighbor_idxs_with_index_type<I: IndexType>(
+    current: I,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+) -> Vec<I> {
+    let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
     let tile_count = width * height;
+    let current = current.index();
     let idx_in_level = current % tile_count;
     let is_top = idx_in_level < width;
     let is_bottom = idx_in_level >= tile_count - width;
     let x = current % width;
     let is_left = x == 0;
     let is_right = x == width - 1;
-    let mut neighbors: Vec<u32> = vec![];
+    let mut neighbors: Vec<I> = vec![];
     if !is_top {
         let top_index = current - width;
-        if grid[top_index as usize] > 0 {
-            neighbors.push(top_index);
+        if grid[top_index] > 0 {
+            neighbors.push(I::new(top_index));
         }
-        if !is_left && grid[top_index as usize - 1] > 0 {
-            neighbors.push(top_index - 1)
+        if !is_left && grid[top_index - 1] > 0 {
+            neighbors.push(I::new(top_index - 1))
         }
-        if !is_right && grid[top_index as usize + 1] > 0 {
-            neighbors.push(top_index + 1)
+        if !is_right && grid[top_index + 1] > 0 {
+            neighbors.push(I::new(top_index + 1))
         }
     }
-    if !is_left && grid[current as usize - 1] > 0 {
-        neighbors.push(current - 1)
+    if !is_left && grid[current - 1] > 0 {
+        neighbors.push(I::new(current - 1))
     }
-    if !is_right && grid[current as usize + 1] > 0 {
-        neighbors.push(current + 1)
+    if !is_right && grid[current + 1] > 0 {
+        neighbors.push(I::new(current + 1))
     }
     if !is_bottom {
         let bottom_index = current + width;
-        if grid[bottom_index as usize] > 0 {
-            neighbors.push(bottom_index)
+        if grid[bottom_index] > 0 {
+            neighbors.push(I::new(bottom_index))
         }
-        if !is_left && grid[bottom_index as usize - 1] > 0 {
-            neighbors.push(bottom_index - 1)
+        if !is_left && grid[bottom_index - 1] > 0 {
+            neighbors.push(I::new(bottom_index - 1))
         }
-        if !is_right && grid[bottom_index as usize + 1] > 0 {
-            neighbors.push(bottom_index + 1)
+        if !is_right && grid[bottom_index + 1] > 0 {
+            neighbors.push(I::new(bottom_index + 1))
         }
     }
     let mut vertical_neighbors = vec![];
     for neighbor in &neighbors {
         if up_stairs_idxs.contains(neighbor) {
-            vertical_neighbors.push(neighbor + tile_count)
+            vertical_neighbors.push(I::new(neighbor.index() + tile_count))
         }
         if down_stairs_idxs.contains(neighbor) {
-            vertical_neighbors.push(neighbor - tile_count)
+            vertical_neighbors.push(I::new(neighbor.index() - tile_count))
         }
     }
     neighbors.append(&mut vertical_neighbors);
@@ -88,18 +180,29 @@ pub fn get_neighbor_idxs(
 }
 
 pub fn create_neighbor_idx_cache(
-    grid: &Vec<u32>,
+    grid: &[u32],
     dimensions: (u32, u32),
     up_stairs_idxs: &HashSet<u32>,
     down_stairs_idxs: &HashSet<u32>,
 ) -> Vec<Vec<u32>> {
+    create_neighbor_idx_cache_with_index_type(grid, dimensions, up_stairs_idxs, down_stairs_idxs)
+}
+
+/// Like `create_neighbor_idx_cache`, with the indices stored as `I`. Every
+/// function that takes the cache uses the same index type for tiles.
+pub fn create_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+) -> Vec<Vec<I>> {
     let mut neighbor_idx_cache = vec![];
     for idx in 0..grid.len() {
         let neighbors = if grid[idx] == 0 {
             vec![]
         } else {
-            get_neighbor_idxs(
-                idx as u32,
+            get_neighbor_idxs_with_index_type(
+                I::new(idx),
                 grid,
                 dimensions,
                 up_stairs_idxs,
@@ -112,26 +215,117 @@ pub fn create_neighbor_idx_cache(
 }
 
 pub fn update_neighbor_idx_cache(
-    grid: &Vec<u32>,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+    neighbors: &mut [Vec<u32>],
+    update_idxs: &[u32],
+) {
+    update_neighbor_idx_cache_with_index_type(
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+        neighbors,
+        update_idxs,
+    )
+}
+
+/// Like `update_neighbor_idx_cache`, for a cache with the indices stored as
+/// `I`.
+pub fn update_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+    neighbors: &mut [Vec<I>],
+    update_idxs: &[I],
+) {
+    for &idx in update_idxs {
+        for neighbor_idx in get_neighbor_idxs_with_index_type(
+            idx,
+            grid,
+            dimensions,
+            up_stairs_idxs,
+            down_stairs_idxs,
+        ) {
+            neighbors[neighbor_idx.index()] = get_neighbor_idxs_with_index_type(
+                neighbor_idx,
+                grid,
+                dimensions,
+                up_stairs_idxs,
+                down_stairs_idxs,
+            );
+        }
+    }
+}
+
+/// Like `create_neighbor_idx_cache`, but builds a `CompactNeighborIdxCache`.
+pub fn create_compact_neighbor_idx_cache(
+    grid: &[u32],
     dimensions: (u32, u32),
     up_stairs_idxs: &HashSet<u32>,
     down_stairs_idxs: &HashSet<u32>,
-    neighbors: &mut Vec<Vec<u32>>,
-    update_idxs: &Vec<u32>,
+) -> CompactNeighborIdxCache {
+    create_compact_neighbor_idx_cache_with_index_type(
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+    )
+}
+
+/// Like `create_compact_neighbor_idx_cache`, with the indices stored as `I`.
+pub fn create_compact_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => get_neighbor_idxs_with_index_type(
+            I::new(idx),
+            grid,
+            dimensions,
+            up_stairs_idxs,
+            down_stairs_idxs,
+        ),
+    })
+}
+
+/// Like `update_neighbor_idx_cache`, for a `CompactNeighborIdxCache`. The
+/// cache is updated in place unless a tile gains or loses neighbors, in which
+/// case it is rebuilt once for all of `update_idxs`.
+pub fn update_compact_neighbor_idx_cache<I: IndexType, O: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+    neighbors: &mut CompactNeighborIdxCache<I, O>,
+    update_idxs: &[I],
 ) {
-    for idx in update_idxs {
-        for neighbor_idx in
-            get_neighbor_idxs(*idx, grid, dimensions, up_stairs_idxs, down_stairs_idxs)
-        {
-            neighbors[neighbor_idx as usize] = get_neighbor_idxs(
+    let mut updates = vec![];
+    for &idx in update_idxs {
+        for neighbor_idx in get_neighbor_idxs_with_index_type(
+            idx,
+            grid,
+            dimensions,
+            up_stairs_idxs,
+            down_stairs_idxs,
+        ) {
+            let neighbor_idxs = get_neighbor_idxs_with_index_type(
                 neighbor_idx,
                 grid,
                 dimensions,
                 up_stairs_idxs,
                 down_stairs_idxs,
             );
+            updates.push((neighbor_idx, neighbor_idxs));
         }
     }
+    neighbors.replace_neighbors(updates);
 }
 
 #[inline(always)]
@@ -139,88 +333,281 @@ fn manhattan(x1: i32, y1: i32, depth1: i32, x2: i32, y2: i32, depth2: i32) -> u3
     ((x1 - x2).abs() + (y1 - y2).abs() + (depth1 - depth2).abs()) as u32
 }
 
-pub fn find_path(
-    start: u32,
-    end: u32,
-    grid: &Vec<u32>,
+pub(crate) fn search<
+    N: NeighborIdxCache + ?Sized,
+    C: Cost,
+    F: Fn(N::Index, N::Index) -> Option<C>,
+    O: SearchObserver<C, N::Index>,
+>(
+    start: N::Index,
+    end: N::Index,
     dimensions: (u32, u32),
-    neighbors: &Vec<Vec<u32>>,
-) -> Vec<u32> {
-    let (width, height) = dimensions;
+    neighbors: &N,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+    observer: O,
+) -> Vec<N::Index> {
+    search_with_buffers(
+        start,
+        end,
+        dimensions,
+        neighbors,
+        get_cost_for_edge,
+        options,
+        observer,
+        &mut SearchBuffers::default(),
+    )
+}
+
+// Like `search`, working in `buffers` rather than allocating its own.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn search_with_buffers<
+    N: NeighborIdxCache + ?Sized,
+    C: Cost,
+    F: Fn(N::Index, N::Index) -> Option<C>,
+    O: SearchObserver<C, N::Index>,
+>(
+    start: N::Index,
+    end: N::Index,
+    dimensions: (u32, u32),
+    neighbors: &N,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+    mut observer: O,
+    buffers: &mut SearchBuffers<C, N::Index>,
+) -> Vec<N::Index> {
+    let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
     let tile_count_per_floor = width * height;
-    let end_x = end % width;
-    let end_y = end % tile_count_per_floor / width;
-    let end_depth = end / tile_count_per_floor;
-    let mut frontier = BinaryHeap::with_capacity(grid.len());
-    let mut cost_so_far = vec![0; grid.len()];
-    let mut came_from = vec![start; grid.len()];
-    cost_so_far[start as usize] = 1;
+    let to_xyz = |idx: N::Index| {
+        let idx = idx.index();
+        (
+            (idx % width) as i32,
+            (idx % tile_count_per_floor / width) as i32,
+            (idx / tile_count_per_floor) as i32,
+        )
+    };
+    let (end_x, end_y, end_depth) = to_xyz(end);
+    buffers.reset(neighbors.tile_count(), start);
+    let SearchBuffers {
+        frontier,
+        cost_so_far,
+        came_from,
+        closed,
+    } = buffers;
+    cost_so_far[start.index()] = Some(C::ZERO);
+    observer.on_push(start, C::ZERO);
     frontier.push(FrontierItem {
-        cost: 0,
+        cost: C::ZERO,
+        tie_breaker: 0,
         position: start,
     });
     let mut found = false;
     while !frontier.is_empty() {
         let current_idx = frontier.pop().unwrap().position;
+        // Tiles improved after being queued leave stale entries behind.
+        if closed[current_idx.index()] {
+            observer.on_skip(current_idx);
+            continue;
+        }
+        closed[current_idx.index()] = true;
         if current_idx == end {
             found = true;
             break;
         }
-        let current_x = current_idx % width;
-        let current_y = current_idx % tile_count_per_floor / width;
-        let current_depth = current_idx / tile_count_per_floor;
-        let neighbor_idxs = &neighbors[current_idx as usize];
-        for idx in 0..neighbor_idxs.len() {
-            let neighbor = neighbor_idxs[idx];
-            let neighbor_cost = grid[neighbor as usize];
-            let neighbor_x = neighbor % width;
-            let neighbor_y = neighbor % tile_count_per_floor / width;
-            let neighbor_depth = neighbor / tile_count_per_floor;
-            let cost = cost_so_far[current_idx as usize]
-                + neighbor_cost
-                + manhattan(
-                    current_x as i32,
-                    current_y as i32,
-                    current_depth as i32,
-                    neighbor_x as i32,
-                    neighbor_y as i32,
-                    neighbor_depth as i32,
+        let current_cost = cost_so_far[current_idx.index()].expect("queued tiles have a cost");
+        observer.on_expand(current_idx, current_cost);
+        let (current_x, current_y, current_depth) = to_xyz(current_idx);
+        let neighbor_idxs = neighbors.get_neighbors(current_idx);
+        for &neighbor in neighbor_idxs {
+            if closed[neighbor.index()] {
+                continue;
+            }
+            let neighbor_cost = match get_cost_for_edge(current_idx, neighbor) {
+                Some(cost) => cost,
+                None => continue,
+            };
+            let (neighbor_x, neighbor_y, neighbor_depth) = to_xyz(neighbor);
+            let distance = C::from_tiles(manhattan(
+                current_x,
+                current_y,
+                current_depth,
+                neighbor_x,
+                neighbor_y,
+                neighbor_depth,
+            ) as f64);
+            let cost = match options
+                .overflow
+                .add(current_cost, neighbor_cost)
+                .and_then(|cost| options.overflow.add(cost, distance))
+            {
+                Some(cost) => cost,
+                None => continue,
+            };
+            let is_cheaper = match cost_so_far[neighbor.index()] {
+                Some(neighbor_cost_so_far) if cost < neighbor_cost_so_far => {
+                    observer.on_relax(neighbor, neighbor_cost_so_far, cost);
+                    true
+                }
+                Some(_) => false,
+                None => true,
+            };
+            if is_cheaper {
+                cost_so_far[neighbor.index()] = Some(cost);
+                observer.on_push(neighbor, cost);
+                let heuristic = manhattan(
+                    end_x,
+                    end_y,
+                    end_depth,
+                    neighbor_x,
+                    neighbor_y,
+                    neighbor_depth,
                 );
-            let neighbor_cost_so_far = cost_so_far[neighbor as usize];
-            if neighbor_cost_so_far == 0 || cost < neighbor_cost_so_far {
-                cost_so_far[neighbor as usize] = cost;
-                let priority = cost
-                    + manhattan(
-                        end_x as i32,
-                        end_y as i32,
-                        end_depth as i32,
-                        neighbor_x as i32,
-                        neighbor_y as i32,
-                        neighbor_depth as i32,
-                    );
+                let priority = cost.saturating_add(C::from_tiles(
+                    heuristic as f64 * options.heuristic_weight as f64,
+                ));
                 frontier.push(FrontierItem {
                     cost: priority,
+                    tie_breaker: options.tie_breaking.rank(
+                        cost.order_key(),
+                        heuristic,
+                        neighbor.index() as u64,
+                        start.index() as u64,
+                        end.index() as u64,
+                        dimensions,
+                    ),
                     position: neighbor,
                 });
-                came_from[neighbor as usize] = current_idx;
+                came_from[neighbor.index()] = current_idx;
             }
         }
     }
-    let mut path: Vec<u32> = Vec::new();
+    let mut path: Vec<N::Index> = Vec::new();
     if found {
         let mut last = end;
         loop {
             path.push(last);
-            last = came_from[last as usize];
+            last = came_from[last.index()];
             if last == start {
                 break;
             }
         }
         path.reverse();
+        observer.on_path_found(&path);
     }
     path
 }
 
+/// Like `find_path`, but the cost of stepping from one tile to a neighbor is
+/// `get_cost_for_edge(from, to)` rather than a value read from a grid.
+/// Returning `None` forbids the step. `neighbors` still decides which tiles
+/// are connected, and the distance moved is added on top of the returned cost.
+/// Returning `Some(0)` makes a step free, such as along a road.
+pub fn find_path_with_edge_cost<
+    N: NeighborIdxCache + ?Sized,
+    C: Cost,
+    F: Fn(N::Index, N::Index) -> Option<C>,
+>(
+    start: N::Index,
+    end: N::Index,
+    dimensions: (u32, u32),
+    neighbors: &N,
+    get_cost_for_edge: F,
+) -> Vec<N::Index> {
+    search(
+        start,
+        end,
+        dimensions,
+        neighbors,
+        get_cost_for_edge,
+        &SearchOptions::default(),
+        (),
+    )
+}
+
+/// Like `find_path_with_edge_cost`, with the search tuned by `options`.
+pub fn find_path_with_edge_cost_and_options<
+    N: NeighborIdxCache + ?Sized,
+    C: Cost,
+    F: Fn(N::Index, N::Index) -> Option<C>,
+>(
+    start: N::Index,
+    end: N::Index,
+    dimensions: (u32, u32),
+    neighbors: &N,
+    get_cost_for_edge: F,
+    options: &SearchOptions,
+) -> Vec<N::Index> {
+    search(
+        start,
+        end,
+        dimensions,
+        neighbors,
+        get_cost_for_edge,
+        options,
+        (),
+    )
+}
+
+/// Finds the cheapest path from `start` to `end`. `neighbors` can be any
+/// `NeighborIdxCache`, and tiles are indexed with its index type.
+pub fn find_path<N: NeighborIdxCache + ?Sized>(
+    start: N::Index,
+    end: N::Index,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    neighbors: &N,
+) -> Vec<N::Index> {
+    find_path_with_edge_cost(start, end, dimensions, neighbors, |_, idx: N::Index| {
+        Some(grid[idx.index()])
+    })
+}
+
+/// Weighted A*: like `find_path`, but the heuristic is multiplied by
+/// `heuristic_weight`. Weights above 1 expand fewer tiles in exchange for
+/// paths that may cost up to `heuristic_weight` times the cheapest one.
+pub fn find_path_weighted<N: NeighborIdxCache + ?Sized>(
+    start: N::Index,
+    end: N::Index,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    neighbors: &N,
+    heuristic_weight: f32,
+) -> Vec<N::Index> {
+    search(
+        start,
+        end,
+        dimensions,
+        neighbors,
+        |_, idx: N::Index| Some(grid[idx.index()]),
+        &SearchOptions {
+            heuristic_weight,
+            ..SearchOptions::default()
+        },
+        (),
+    )
+}
+
+/// Like `find_path`, with the heuristic weight and tie-breaking set by
+/// `options`.
+pub fn find_path_with_options<N: NeighborIdxCache + ?Sized>(
+    start: N::Index,
+    end: N::Index,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    neighbors: &N,
+    options: &SearchOptions,
+) -> Vec<N::Index> {
+    search(
+        start,
+        end,
+        dimensions,
+        neighbors,
+        |_, idx: N::Index| Some(grid[idx.index()]),
+        options,
+        (),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -366,6 +753,235 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_uses_edge_costs() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, (3, 3), &HashSet::new(), &HashSet::new());
+        let path =
+            find_path_with_edge_cost(0, 2, (3, 3), &neighbors, |_, to| Some(grid[to as usize]));
+        assert_eq!(path, vec![1, 2]);
+        let path = find_path_with_edge_cost(0, 2, (3, 3), &neighbors, |_, to| match to {
+            1 => Some(10),
+            _ => Some(grid[to as usize]),
+        });
+        assert_eq!(path, vec![4, 2]);
+        let path =
+            find_path_with_edge_cost(0, 2, (3, 3), &neighbors, |from, to| match (from, to) {
+                (1, 2) => None,
+                _ => Some(grid[to as usize]),
+            });
+        assert_eq!(path, vec![4, 2]);
+    }
+
+    #[test]
+    fn it_paths_over_zero_cost_tiles() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        // The bottom row is a road, which is free to enter apart from the
+        // distance moved.
+        #[rustfmt::skip]
+        let costs: Vec<u32> = vec![
+            1, 9, 1,
+            0, 0, 0,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, (3, 2), &HashSet::new(), &HashSet::new());
+        let path =
+            find_path_with_edge_cost(0, 2, (3, 2), &neighbors, |_, to| Some(costs[to as usize]));
+        assert!(!path.contains(&1));
+        assert_eq!(path.last(), Some(&2));
+        let path = find_path_with_edge_cost(0, 2, (3, 2), &neighbors, |_, _| Some(0u32));
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[test]
+    fn it_weights_the_heuristic() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let up_stairs_idxs = HashSet::new();
+        let down_stairs_idxs = HashSet::new();
+        let dimensions = (7, 7);
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &up_stairs_idxs, &down_stairs_idxs);
+        let path = find_path_weighted(0, 48, &grid, dimensions, &neighbors, 1.0);
+        assert_eq!(path, find_path(0, 48, &grid, dimensions, &neighbors));
+        let path = find_path_weighted(0, 48, &grid, dimensions, &neighbors, 3.0);
+        assert_eq!(path.last(), Some(&48));
+    }
+
+    #[test]
+    fn it_accumulates_other_cost_types() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, (3, 3), &HashSet::new(), &HashSet::new());
+        let path = find_path_with_edge_cost(0, 2, (3, 3), &neighbors, |_, to| {
+            Some(Fixed::from_f64(if to == 1 { 2.5 } else { 0.25 }))
+        });
+        assert_eq!(path, vec![4, 2]);
+        let path = find_path_with_edge_cost(0, 2, (3, 3), &neighbors, |_, to| {
+            Some(OrderedFloat(if to == 1 { 2.0 } else { 0.25 }))
+        });
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[test]
+    fn it_handles_cost_overflow() {
+        let grid = vec![1, 1, 1];
+        let neighbors = create_neighbor_idx_cache(&grid, (3, 1), &HashSet::new(), &HashSet::new());
+        let get_cost_for_edge = |_, to| Some(if to == 1 { u16::MAX } else { 1 });
+        let saturating = SearchOptions {
+            overflow: OverflowPolicy::Saturating,
+            ..SearchOptions::default()
+        };
+        let path = find_path_with_edge_cost_and_options(
+            0,
+            2,
+            (3, 1),
+            &neighbors,
+            get_cost_for_edge,
+            &SearchOptions::default(),
+        );
+        assert_eq!(path, vec![]);
+        let path = find_path_with_edge_cost_and_options(
+            0,
+            2,
+            (3, 1),
+            &neighbors,
+            get_cost_for_edge,
+            &saturating,
+        );
+        assert_eq!(path, vec![1, 2]);
+    }
+
+    #[test]
+    fn it_uses_other_index_types() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1,
+
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let dimensions = (3, 3);
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &HashSet::from([8]), &HashSet::from([17]));
+        let expected = find_path(0, 9, &grid, dimensions, &neighbors);
+        assert_eq!(expected.last(), Some(&9));
+        let neighbors = create_neighbor_idx_cache_with_index_type::<u16>(
+            &grid,
+            dimensions,
+            &HashSet::from([8]),
+            &HashSet::from([17]),
+        );
+        let path = find_path(0, 9, &grid, dimensions, &neighbors);
+        assert_eq!(
+            path,
+            expected.iter().map(|&idx| idx as u16).collect::<Vec<_>>()
+        );
+        let neighbors = create_neighbor_idx_cache_with_index_type::<u64>(
+            &grid,
+            dimensions,
+            &HashSet::from([8]),
+            &HashSet::from([17]),
+        );
+        let path = find_path(0, 9, &grid, dimensions, &neighbors);
+        assert_eq!(
+            path,
+            expected.iter().map(|&idx| idx as u64).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_uses_compact_neighbor_caches() {
+        #[rustfmt::skip]
+        let mut grid = vec![
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1,
+
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+        ];
+        let dimensions = (3, 3);
+        let up_stairs_idxs = HashSet::from([8]);
+        let down_stairs_idxs = HashSet::from([17]);
+        let mut neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &up_stairs_idxs, &down_stairs_idxs);
+        let mut compact = create_compact_neighbor_idx_cache(
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+        );
+        let path = find_path(0, 9, &grid, dimensions, &compact);
+        assert_eq!(path, find_path(0, 9, &grid, dimensions, &neighbors));
+        grid[5] = 0;
+        update_neighbor_idx_cache(
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+            &mut neighbors,
+            &[5],
+        );
+        update_compact_neighbor_idx_cache(
+            &grid,
+            dimensions,
+            &up_stairs_idxs,
+            &down_stairs_idxs,
+            &mut compact,
+            &[5],
+        );
+        for idx in 0..grid.len() as u32 {
+            assert_eq!(compact.get_neighbors(idx), neighbors.get_neighbors(idx));
+        }
+        let path = find_path(0, 9, &grid, dimensions, &compact);
+        assert!(!path.contains(&5));
+        assert_eq!(path.last(), Some(&9));
+    }
+
+    #[bench]
+    fn bench_it_crosses_rough_ground(b: &mut Bencher) {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 9, 1,
+            9, 9, 9, 9, 9, 9, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 9, 9, 9, 9, 9, 9,
+            1, 1, 1, 1, 1, 1, 1,
+            9, 1, 1, 1, 1, 1, 1,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, (7, 7), &HashSet::new(), &HashSet::new());
+        let (_, stats) =
+            measure(|observer| find_path_with_observer(0, 48, &grid, (7, 7), &neighbors, observer));
+        assert_eq!((stats.expansions, stats.stale_entries), (41, 10));
+        b.iter(|| find_path(0, 48, &grid, (7, 7), &neighbors));
+    }
+
     #[bench]
     fn bench_it_avoids_walls(b: &mut Bencher) {
         #[rustfmt::skip]
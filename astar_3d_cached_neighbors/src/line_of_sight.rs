@@ -0,0 +1,243 @@
+// Line of sight never crosses floors, so everything here works on the floor
+// of the first tile and translates indices in and out of that floor.
+
+#[inline(always)]
+fn is_open(x: i32, y: i32, floor: &[u32], width: u32) -> bool {
+    floor[(y as u32 * width + x as u32) as usize] > 0
+}
+
+#[inline(always)]
+fn to_idx(x: i32, y: i32, width: u32) -> u32 {
+    y as u32 * width + x as u32
+}
+
+#[inline(always)]
+fn get_floor(idx: u32, grid: &[u32], dimensions: (u32, u32)) -> (u32, &[u32]) {
+    let (width, height) = dimensions;
+    let tile_count = width * height;
+    let floor_start = idx / tile_count * tile_count;
+    (
+        floor_start,
+        &grid[floor_start as usize..(floor_start + tile_count) as usize],
+    )
+}
+
+// Bresenham line between tile centres on one floor. Every step is a legal
+// move for the neighbor rules, which allow diagonals past wall corners. Calls
+// `visit` with every tile after `from`, stopping at and returning the first
+// wall.
+pub(crate) fn walk_line<F: FnMut(u32)>(
+    from: u32,
+    to: u32,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    mut visit: F,
+) -> Option<u32> {
+    let (width, _) = dimensions;
+    let (floor_start, floor) = get_floor(from, grid, dimensions);
+    debug_assert_eq!(floor_start, get_floor(to, grid, dimensions).0);
+    let (from, to) = (from - floor_start, to - floor_start);
+    let (mut x, mut y) = ((from % width) as i32, (from / width) as i32);
+    let (end_x, end_y) = ((to % width) as i32, (to / width) as i32);
+    let dx = (end_x - x).abs();
+    let dy = -(end_y - y).abs();
+    let step_x = if x < end_x { 1 } else { -1 };
+    let step_y = if y < end_y { 1 } else { -1 };
+    let mut error = dx + dy;
+    if !is_open(x, y, floor, width) {
+        return Some(floor_start + from);
+    }
+    while x != end_x || y != end_y {
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+        if !is_open(x, y, floor, width) {
+            return Some(floor_start + to_idx(x, y, width));
+        }
+        visit(floor_start + to_idx(x, y, width));
+    }
+    None
+}
+
+/// Walks the straight line from `from` to `to`, which must be on the same
+/// floor, returning the first wall hit or `None` if `to` is reached. Like
+/// `find_path`, diagonal steps may cut past wall corners.
+pub fn raycast(from: u32, to: u32, grid: &[u32], dimensions: (u32, u32)) -> Option<u32> {
+    walk_line(from, to, grid, dimensions, |_| {})
+}
+
+/// Tiles on different floors are never in line of sight of each other.
+pub fn has_line_of_sight(from: u32, to: u32, grid: &[u32], dimensions: (u32, u32)) -> bool {
+    let (width, height) = dimensions;
+    let tile_count = width * height;
+    from / tile_count == to / tile_count && raycast(from, to, grid, dimensions).is_none()
+}
+
+// Multipliers transforming the first octant into each of the eight octants.
+const OCTANTS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+#[allow(clippy::too_many_arguments)]
+fn cast_light(
+    origin: (i32, i32),
+    row: i32,
+    mut start_slope: f32,
+    end_slope: f32,
+    radius: i32,
+    octant: (i32, i32, i32, i32),
+    floor: &[u32],
+    dimensions: (u32, u32),
+    visible: &mut Vec<bool>,
+) {
+    if start_slope < end_slope {
+        return;
+    }
+    let (width, height) = dimensions;
+    let (xx, xy, yx, yy) = octant;
+    let mut next_start_slope = start_slope;
+    for distance in row..=radius {
+        let dy = -distance;
+        let mut blocked = false;
+        for dx in -distance..=0 {
+            let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+            if start_slope < right_slope {
+                continue;
+            }
+            if end_slope > left_slope {
+                break;
+            }
+            let x = origin.0 + dx * xx + dy * xy;
+            let y = origin.1 + dx * yx + dy * yy;
+            if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                continue;
+            }
+            if dx * dx + dy * dy <= radius * radius {
+                visible[to_idx(x, y, width) as usize] = true;
+            }
+            let is_wall = !is_open(x, y, floor, width);
+            if blocked {
+                if is_wall {
+                    next_start_slope = right_slope;
+                } else {
+                    blocked = false;
+                    start_slope = next_start_slope;
+                }
+            } else if is_wall && distance < radius {
+                blocked = true;
+                cast_light(
+                    origin,
+                    distance + 1,
+                    start_slope,
+                    left_slope,
+                    radius,
+                    octant,
+                    floor,
+                    dimensions,
+                    visible,
+                );
+                next_start_slope = right_slope;
+            }
+        }
+        if blocked {
+            break;
+        }
+    }
+}
+
+/// Recursive shadowcasting from `origin` across its own floor. Returns every
+/// tile within `radius` that can be seen, including the walls that block the
+/// view, in index order.
+pub fn field_of_view(origin: u32, radius: u32, grid: &[u32], dimensions: (u32, u32)) -> Vec<u32> {
+    let (width, _) = dimensions;
+    let (floor_start, floor) = get_floor(origin, grid, dimensions);
+    let origin = origin - floor_start;
+    let mut visible = vec![false; floor.len()];
+    visible[origin as usize] = true;
+    let origin_xy = ((origin % width) as i32, (origin / width) as i32);
+    for octant in OCTANTS {
+        cast_light(
+            origin_xy,
+            1,
+            1.0,
+            0.0,
+            radius as i32,
+            octant,
+            floor,
+            dimensions,
+            &mut visible,
+        );
+    }
+    (0..floor.len() as u32)
+        .filter(|idx| visible[*idx as usize])
+        .map(|idx| floor_start + idx)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_sees_across_a_floor() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+
+            1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        assert!(has_line_of_sight(5, 9, &grid, (5, 3)));
+        assert!(!has_line_of_sight(20, 24, &grid, (5, 3)));
+        assert_eq!(raycast(20, 24, &grid, (5, 3)), Some(22));
+    }
+
+    #[test]
+    fn it_does_not_see_between_floors() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1,
+            1, 1,
+
+            1, 1,
+            1, 1,
+        ];
+        assert!(!has_line_of_sight(0, 4, &grid, (2, 2)));
+    }
+
+    #[test]
+    fn it_only_sees_its_own_floor() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+
+            1, 1, 1,
+            1, 1, 0,
+            1, 1, 1,
+        ];
+        assert_eq!(field_of_view(4, 1, &grid, (3, 3)), vec![1, 3, 4, 5, 7]);
+        assert_eq!(
+            field_of_view(12, 2, &grid, (3, 3)),
+            vec![9, 10, 12, 13, 14, 15, 16]
+        );
+    }
+}
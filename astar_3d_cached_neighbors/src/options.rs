@@ -0,0 +1,167 @@
+use crate::OverflowPolicy;
+
+/// How to order tiles whose priorities are equal. Every strategy only uses
+/// integer arithmetic and falls back to the tile index, so the same inputs
+/// give the same path on every platform.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TieBreaking {
+    /// Prefer higher tile indices. This is the original ordering.
+    #[default]
+    Position,
+    /// Prefer the tile that is furthest from the start.
+    HigherCost,
+    /// Prefer the tile that is estimated to be closest to the end.
+    LowerHeuristic,
+    /// Prefer the tile closest to the straight line from the start to the end,
+    /// which avoids staircase paths on open ground.
+    Straightness,
+}
+
+impl TieBreaking {
+    // Among equal priorities the lowest rank is expanded first.
+    #[inline(always)]
+    pub(crate) fn rank(
+        self,
+        cost: u64,
+        heuristic: u32,
+        idx: u64,
+        start: u64,
+        end: u64,
+        dimensions: (u32, u32),
+    ) -> u64 {
+        match self {
+            TieBreaking::Position => 0,
+            TieBreaking::HigherCost => u64::MAX - cost,
+            TieBreaking::LowerHeuristic => heuristic as u64,
+            TieBreaking::Straightness => {
+                let (width, height) = (dimensions.0 as u64, dimensions.1 as u64);
+                let tile_count = width * height;
+                let to_xyz = |idx: u64| {
+                    (
+                        (idx % width) as i64,
+                        (idx % tile_count / width) as i64,
+                        (idx / tile_count) as i64,
+                    )
+                };
+                let (end_x, end_y, end_depth) = to_xyz(end);
+                let (start_x, start_y, start_depth) = to_xyz(start);
+                let (idx_x, idx_y, idx_depth) = to_xyz(idx);
+                let line = (start_x - end_x, start_y - end_y, start_depth - end_depth);
+                let offset = (idx_x - end_x, idx_y - end_y, idx_depth - end_depth);
+                (line.1 * offset.2 - line.2 * offset.1).unsigned_abs()
+                    + (line.2 * offset.0 - line.0 * offset.2).unsigned_abs()
+                    + (line.0 * offset.1 - line.1 * offset.0).unsigned_abs()
+            }
+        }
+    }
+}
+
+/// Settings for `find_path_with_options`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchOptions {
+    /// Multiplies the heuristic, see `find_path_weighted`.
+    pub heuristic_weight: f32,
+    pub tie_breaking: TieBreaking,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            heuristic_weight: 1.0,
+            tie_breaking: TieBreaking::default(),
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_neighbor_idx_cache, find_path, find_path_with_options};
+    use hashbrown::HashSet;
+
+    const STRATEGIES: [TieBreaking; 4] = [
+        TieBreaking::Position,
+        TieBreaking::HigherCost,
+        TieBreaking::LowerHeuristic,
+        TieBreaking::Straightness,
+    ];
+
+    #[test]
+    fn it_ranks_tiles_on_the_line_first() {
+        let straightness = TieBreaking::Straightness;
+        assert_eq!(straightness.rank(0, 0, 16, 0, 32, (8, 5)), 0);
+        assert_eq!(straightness.rank(0, 0, 9, 0, 32, (8, 5)), 4);
+        assert_eq!(straightness.rank(0, 0, 40, 0, 80, (8, 5)), 0);
+        assert!(straightness.rank(0, 0, 41, 0, 80, (8, 5)) > 0);
+    }
+
+    #[test]
+    fn it_keeps_the_original_ordering_by_default() {
+        let grid = vec![1; 35];
+        let dimensions = (7, 5);
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &HashSet::new(), &HashSet::new());
+        assert_eq!(
+            find_path_with_options(
+                0,
+                34,
+                &grid,
+                dimensions,
+                &neighbors,
+                &SearchOptions::default()
+            ),
+            find_path(0, 34, &grid, dimensions, &neighbors)
+        );
+    }
+
+    #[test]
+    fn it_breaks_ties_towards_the_straight_line() {
+        let grid = vec![1; 35];
+        let dimensions = (7, 5);
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &HashSet::new(), &HashSet::new());
+        let options = SearchOptions {
+            tie_breaking: TieBreaking::Straightness,
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            find_path_with_options(0, 34, &grid, dimensions, &neighbors, &options),
+            vec![8, 9, 17, 25, 26, 34]
+        );
+    }
+
+    #[test]
+    fn it_finds_equally_short_paths_with_every_strategy() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let dimensions = (7, 7);
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &HashSet::new(), &HashSet::new());
+        let shortest = find_path(0, 48, &grid, dimensions, &neighbors).len();
+        for tie_breaking in STRATEGIES {
+            let options = SearchOptions {
+                tie_breaking,
+                ..SearchOptions::default()
+            };
+            let path = find_path_with_options(0, 48, &grid, dimensions, &neighbors, &options);
+            assert_eq!(path.len(), shortest);
+            assert_eq!(path.last(), Some(&48));
+            assert_eq!(
+                path,
+                find_path_with_options(0, 48, &grid, dimensions, &neighbors, &options)
+            );
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{get_neighbor_idxs_with_index_type, CompactNeighborIdxCache, IndexType};
+use hashbrown::HashSet;
+use rayon::prelude::*;
+
+/// Like `create_neighbor_idx_cache`, looking up the neighbors of each tile
+/// across rayon's threads. The cache built is the same as the serial one.
+pub fn par_create_neighbor_idx_cache(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+) -> Vec<Vec<u32>> {
+    par_create_neighbor_idx_cache_with_index_type(
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+    )
+}
+
+/// Like `par_create_neighbor_idx_cache`, with the indices stored as `I`.
+pub fn par_create_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+) -> Vec<Vec<I>> {
+    (0..grid.len())
+        .into_par_iter()
+        .map(|idx| match grid[idx] {
+            0 => vec![],
+            _ => get_neighbor_idxs_with_index_type(
+                I::new(idx),
+                grid,
+                dimensions,
+                up_stairs_idxs,
+                down_stairs_idxs,
+            ),
+        })
+        .collect()
+}
+
+/// Like `create_compact_neighbor_idx_cache`, looking up the neighbors of each
+/// tile across rayon's threads.
+pub fn par_create_compact_neighbor_idx_cache(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<u32>,
+    down_stairs_idxs: &HashSet<u32>,
+) -> CompactNeighborIdxCache {
+    par_create_compact_neighbor_idx_cache_with_index_type(
+        grid,
+        dimensions,
+        up_stairs_idxs,
+        down_stairs_idxs,
+    )
+}
+
+/// Like `par_create_compact_neighbor_idx_cache`, with the indices stored as
+/// `I`.
+pub fn par_create_compact_neighbor_idx_cache_with_index_type<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    up_stairs_idxs: &HashSet<I>,
+    down_stairs_idxs: &HashSet<I>,
+) -> CompactNeighborIdxCache<I> {
+    CompactNeighborIdxCache::par_from_fn(grid.len(), |idx| match grid[idx] {
+        0 => vec![],
+        _ => get_neighbor_idxs_with_index_type(
+            I::new(idx),
+            grid,
+            dimensions,
+            up_stairs_idxs,
+            down_stairs_idxs,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_compact_neighbor_idx_cache, create_neighbor_idx_cache};
+
+    // A world of `floors` floors with scattered walls, and a staircase every
+    // 97 tiles between each floor and the one above it.
+    fn create_world(dimensions: (u32, u32), floors: u32) -> (Vec<u32>, HashSet<u32>, HashSet<u32>) {
+        let tile_count = dimensions.0 * dimensions.1;
+        let grid: Vec<u32> = (0..tile_count * floors)
+            .map(|idx| match idx % 7 {
+                3 if idx % 97 != 0 => 0,
+                cost => cost % 3 + 1,
+            })
+            .collect();
+        let up_stairs_idxs: HashSet<u32> = (0..tile_count * (floors - 1)).step_by(97).collect();
+        let down_stairs_idxs = up_stairs_idxs.iter().map(|idx| idx + tile_count).collect();
+        (grid, up_stairs_idxs, down_stairs_idxs)
+    }
+
+    #[test]
+    fn it_builds_the_same_caches_in_parallel() {
+        // Enough tiles for several runs, with the last one cut short.
+        let dimensions = (50, 41);
+        let (grid, up_stairs_idxs, down_stairs_idxs) = create_world(dimensions, 3);
+        assert_eq!(
+            par_create_neighbor_idx_cache(&grid, dimensions, &up_stairs_idxs, &down_stairs_idxs),
+            create_neighbor_idx_cache(&grid, dimensions, &up_stairs_idxs, &down_stairs_idxs)
+        );
+        assert_eq!(
+            par_create_compact_neighbor_idx_cache(
+                &grid,
+                dimensions,
+                &up_stairs_idxs,
+                &down_stairs_idxs
+            )
+            .to_bytes(),
+            create_compact_neighbor_idx_cache(
+                &grid,
+                dimensions,
+                &up_stairs_idxs,
+                &down_stairs_idxs
+            )
+            .to_bytes()
+        );
+    }
+}
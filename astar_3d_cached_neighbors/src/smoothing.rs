@@ -0,0 +1,171 @@
+use crate::line_of_sight::{has_line_of_sight, walk_line};
+
+#[inline(always)]
+fn delta(from: u32, to: u32, dimensions: (u32, u32)) -> (i64, i64, i64) {
+    let (width, height) = dimensions;
+    let tile_count = width * height;
+    (
+        (to % width) as i64 - (from % width) as i64,
+        (to % tile_count / width) as i64 - (from % tile_count / width) as i64,
+        (to / tile_count) as i64 - (from / tile_count) as i64,
+    )
+}
+
+// Steps between floors are never merged, so stairs always stay in the path.
+#[inline(always)]
+fn is_collinear(previous: u32, current: u32, next: u32, dimensions: (u32, u32)) -> bool {
+    let (ax, ay, az) = delta(previous, current, dimensions);
+    let (bx, by, bz) = delta(current, next, dimensions);
+    az == 0 && bz == 0 && ax * by == ay * bx && ax * bx + ay * by > 0
+}
+
+/// Drops waypoints that sit on a straight line between their neighbors,
+/// leaving only the turning points, floor changes and the end of `path`.
+pub fn remove_collinear_points(
+    start: u32,
+    path: &[u32],
+    grid: &[u32],
+    dimensions: (u32, u32),
+) -> Vec<u32> {
+    let mut simplified = Vec::with_capacity(path.len());
+    let mut anchor = start;
+    for (idx, &current) in path.iter().enumerate() {
+        match path.get(idx + 1) {
+            Some(&next)
+                if is_collinear(anchor, current, next, dimensions)
+                    && has_line_of_sight(anchor, next, grid, dimensions) => {}
+            _ => {
+                simplified.push(current);
+                anchor = current;
+            }
+        }
+    }
+    simplified
+}
+
+/// String pulling: skips every waypoint that can be bypassed in a straight
+/// line on the same floor. With `turning_points_only` the remaining waypoints
+/// are returned, otherwise the straight lines between them are walked so the
+/// result steps tile by tile like `find_path` output.
+pub fn smooth_path(
+    start: u32,
+    path: &[u32],
+    grid: &[u32],
+    dimensions: (u32, u32),
+    turning_points_only: bool,
+) -> Vec<u32> {
+    let (width, height) = dimensions;
+    let tile_count = width * height;
+    let mut waypoints = vec![];
+    let mut anchor = start;
+    let mut previous = start;
+    for &current in path {
+        if previous != anchor && !has_line_of_sight(anchor, current, grid, dimensions) {
+            waypoints.push(previous);
+            anchor = previous;
+        }
+        previous = current;
+    }
+    if let Some(&end) = path.last() {
+        waypoints.push(end);
+    }
+    if turning_points_only {
+        return waypoints;
+    }
+    let mut tiles = Vec::with_capacity(path.len());
+    let mut from = start;
+    for to in waypoints {
+        if from / tile_count == to / tile_count {
+            walk_line(from, to, grid, dimensions, |idx| tiles.push(idx));
+        } else {
+            tiles.push(to);
+        }
+        from = to;
+    }
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_neighbor_idx_cache, find_path};
+    use hashbrown::HashSet;
+
+    #[test]
+    fn it_removes_collinear_points() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let dimensions = (7, 7);
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &HashSet::new(), &HashSet::new());
+        let path = find_path(0, 48, &grid, dimensions, &neighbors);
+        assert_eq!(
+            remove_collinear_points(0, &path, &grid, dimensions),
+            vec![8, 29, 45, 48]
+        );
+    }
+
+    #[test]
+    fn it_keeps_stairs_when_smoothing_across_floors() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 1, 0, 1,
+            1, 1, 0, 1, 1, 0, 1,
+            1, 1, 0, 0, 0, 0, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let dimensions = (7, 7);
+        let up_stairs_idxs = HashSet::from([24, 87]);
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &up_stairs_idxs, &HashSet::new());
+        let path = find_path(0, 146, &grid, dimensions, &neighbors);
+        assert_eq!(
+            path,
+            vec![1, 2, 10, 18, 73, 67, 59, 51, 57, 64, 71, 78, 86, 136, 144, 145, 146]
+        );
+        assert_eq!(
+            smooth_path(0, &path, &grid, dimensions, true),
+            vec![2, 18, 73, 67, 51, 64, 78, 86, 136, 146]
+        );
+        let tiles = smooth_path(0, &path, &grid, dimensions, false);
+        assert_eq!(
+            tiles,
+            vec![1, 2, 10, 18, 73, 67, 59, 51, 57, 64, 71, 78, 86, 136, 137, 145, 146]
+        );
+        assert!(tiles.iter().all(|&idx| grid[idx as usize] > 0));
+    }
+
+    #[test]
+    fn it_leaves_empty_paths_empty() {
+        let grid = vec![1, 1, 1, 1];
+        assert_eq!(remove_collinear_points(0, &[], &grid, (2, 2)), vec![]);
+        assert_eq!(smooth_path(0, &[], &grid, (2, 2), false), vec![]);
+    }
+}
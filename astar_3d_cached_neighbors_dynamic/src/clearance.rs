@@ -0,0 +1,178 @@
+use crate::{find_path_with_edge_cost, IndexType, NeighborIdxCache};
+
+// True clearance: the size of the largest open square on the same floor whose
+// top left corner is `idx`, capped at `max_clearance`. Relies on the tiles to
+// the right of and below `idx` already being filled in.
+#[inline(always)]
+fn get_clearance(
+    idx: usize,
+    grid: &[u32],
+    dimensions: (u32, u32),
+    max_clearance: u32,
+    clearance: &[u32],
+) -> u32 {
+    if grid[idx] == 0 {
+        return 0;
+    }
+    let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
+    let tile_count = width * height;
+    let is_right = idx % width == width - 1;
+    let is_bottom = idx % tile_count >= tile_count - width;
+    if is_right || is_bottom {
+        return max_clearance.min(1);
+    }
+    let right = clearance[idx + 1];
+    let bottom = clearance[idx + width];
+    let bottom_right = clearance[idx + width + 1];
+    max_clearance.min(right.min(bottom).min(bottom_right) + 1)
+}
+
+/// Builds a clearance map for `find_path_with_clearance`. Each tile holds the
+/// size of the largest square agent that fits on its floor with its top left
+/// corner on that tile, or 0 for walls. Values are capped at `max_clearance`,
+/// which only needs to be as large as the biggest agent.
+pub fn create_clearance_map(grid: &[u32], dimensions: (u32, u32), max_clearance: u32) -> Vec<u32> {
+    let mut clearance = vec![0; grid.len()];
+    for idx in (0..grid.len()).rev() {
+        clearance[idx] = get_clearance(idx, grid, dimensions, max_clearance, &clearance);
+    }
+    clearance
+}
+
+/// Refreshes `clearance` after the tiles in `update_idxs` changed in `grid`.
+/// Only tiles up to `max_clearance` to the left of and above a change can be
+/// affected, so just those are recalculated.
+pub fn update_clearance_map<I: IndexType>(
+    grid: &[u32],
+    dimensions: (u32, u32),
+    max_clearance: u32,
+    clearance: &mut [u32],
+    update_idxs: &[I],
+) {
+    let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
+    let tile_count = width * height;
+    let reach = max_clearance.saturating_sub(1) as usize;
+    let mut affected_idxs = vec![];
+    for idx in update_idxs {
+        let idx = idx.index();
+        let floor_start = idx / tile_count * tile_count;
+        let x = idx % width;
+        let y = idx % tile_count / width;
+        for affected_y in y.saturating_sub(reach)..=y {
+            for affected_x in x.saturating_sub(reach)..=x {
+                affected_idxs.push(floor_start + affected_y * width + affected_x);
+            }
+        }
+    }
+    affected_idxs.sort_unstable();
+    affected_idxs.dedup();
+    for idx in affected_idxs.into_iter().rev() {
+        clearance[idx] = get_clearance(idx, grid, dimensions, max_clearance, clearance);
+    }
+}
+
+/// Like `find_path`, but for a square agent `agent_size` tiles across whose
+/// top left corner is the tile being pathed. Only tiles with at least that
+/// much clearance are used.
+pub fn find_path_with_clearance<N: NeighborIdxCache + ?Sized, F: Fn(usize) -> u32>(
+    start: N::Index,
+    end: N::Index,
+    get_difficulty_for_idx: F,
+    dimensions: (u32, u32),
+    neighbors: &N,
+    clearance: &[u32],
+    agent_size: u32,
+) -> Vec<N::Index> {
+    find_path_with_edge_cost(start, end, dimensions, neighbors, |_, idx: N::Index| {
+        (clearance[idx.index()] >= agent_size).then(|| get_difficulty_for_idx(idx.index()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_neighbor_idx_cache;
+    use hashbrown::HashSet;
+
+    #[test]
+    fn it_creates_a_clearance_map_per_floor() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 1,
+
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1,
+        ];
+        #[rustfmt::skip]
+        let expected = vec![
+            3, 2, 1,
+            2, 2, 1,
+            1, 1, 1,
+
+            1, 1, 1,
+            1, 0, 1,
+            1, 1, 1,
+        ];
+        assert_eq!(create_clearance_map(&grid, (3, 3), 3), expected);
+    }
+
+    #[test]
+    fn it_updates_the_clearance_map() {
+        #[rustfmt::skip]
+        let mut grid = vec![
+            1, 1, 1, 1,
+            1, 1, 1, 1,
+            1, 1, 1, 1,
+            1, 1, 1, 1,
+        ];
+        let mut clearance = create_clearance_map(&grid, (4, 4), 3);
+        grid[10] = 0;
+        grid[3] = 0;
+        update_clearance_map(&grid, (4, 4), 3, &mut clearance, &[10u32, 3]);
+        assert_eq!(clearance, create_clearance_map(&grid, (4, 4), 3));
+        grid[10] = 1;
+        update_clearance_map(&grid, (4, 4), 3, &mut clearance, &[10u64]);
+        assert_eq!(clearance, create_clearance_map(&grid, (4, 4), 3));
+    }
+
+    #[test]
+    fn it_keeps_large_agents_out_of_narrow_gaps() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            0, 0, 1, 0, 0, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1, 1, 1,
+        ];
+        let dimensions = (7, 5);
+        let neighbors =
+            create_neighbor_idx_cache(&grid, dimensions, &HashSet::new(), &HashSet::new());
+        let clearance = create_clearance_map(&grid, dimensions, 2);
+        let path = find_path_with_clearance(
+            0,
+            21,
+            |idx| grid[idx],
+            dimensions,
+            &neighbors,
+            &clearance,
+            1,
+        );
+        assert!(path.contains(&16));
+        let path = find_path_with_clearance(
+            0,
+            21,
+            |idx| grid[idx],
+            dimensions,
+            &neighbors,
+            &clearance,
+            2,
+        );
+        assert!(path.contains(&19));
+        assert!(path.iter().all(|&idx| clearance[idx as usize] >= 2));
+        assert_eq!(path.last(), Some(&21));
+    }
+}
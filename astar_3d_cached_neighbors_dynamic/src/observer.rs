@@ -0,0 +1,176 @@
+use crate::{search, IndexType, NeighborIdxCache, SearchOptions};
+
+/// Receives events as a search runs, for recording traces, collecting
+/// statistics or drawing a search live. Every method does nothing by default,
+/// so only the events of interest need implementing. Tiles are given with the
+/// neighbor cache's index type. Searches are generic over their observer, so
+/// the `()` observer used by `find_path` costs nothing.
+pub trait SearchObserver<C = u32, I = u32> {
+    /// A tile was queued with the cost of reaching it. Tiles are queued again
+    /// whenever a cheaper way to them is found.
+    #[inline(always)]
+    fn on_push(&mut self, _idx: I, _cost: C) {}
+
+    /// A tile was taken off the queue and its neighbors are about to be looked
+    /// at.
+    #[inline(always)]
+    fn on_expand(&mut self, _idx: I, _cost: C) {}
+
+    /// A cheaper way to a queued tile was found. This is followed by the tile
+    /// being pushed again.
+    #[inline(always)]
+    fn on_relax(&mut self, _idx: I, _old_cost: C, _new_cost: C) {}
+
+    /// An entry left on the queue for a tile that has since been expanded
+    /// through a cheaper way was taken off and skipped.
+    #[inline(always)]
+    fn on_skip(&mut self, _idx: I) {}
+
+    /// The end was reached, with the path that will be returned.
+    #[inline(always)]
+    fn on_path_found(&mut self, _path: &[I]) {}
+}
+
+impl<C, I> SearchObserver<C, I> for () {}
+
+impl<C, I, O: SearchObserver<C, I>> SearchObserver<C, I> for &mut O {
+    #[inline(always)]
+    fn on_push(&mut self, idx: I, cost: C) {
+        (**self).on_push(idx, cost)
+    }
+
+    #[inline(always)]
+    fn on_expand(&mut self, idx: I, cost: C) {
+        (**self).on_expand(idx, cost)
+    }
+
+    #[inline(always)]
+    fn on_relax(&mut self, idx: I, old_cost: C, new_cost: C) {
+        (**self).on_relax(idx, old_cost, new_cost)
+    }
+
+    #[inline(always)]
+    fn on_skip(&mut self, idx: I) {
+        (**self).on_skip(idx)
+    }
+
+    #[inline(always)]
+    fn on_path_found(&mut self, path: &[I]) {
+        (**self).on_path_found(path)
+    }
+}
+
+/// Like `find_path`, telling `observer` about each step of the search.
+pub fn find_path_with_observer<
+    N: NeighborIdxCache + ?Sized,
+    F: Fn(usize) -> u32,
+    O: SearchObserver<u32, N::Index>,
+>(
+    start: N::Index,
+    end: N::Index,
+    get_difficulty_for_idx: F,
+    dimensions: (u32, u32),
+    neighbors: &N,
+    observer: O,
+) -> Vec<N::Index> {
+    search(
+        start,
+        end,
+        dimensions,
+        neighbors,
+        |_, idx: N::Index| Some(get_difficulty_for_idx(idx.index())),
+        &SearchOptions::default(),
+        observer,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_neighbor_idx_cache, find_path};
+    use hashbrown::HashSet;
+
+    #[derive(Default)]
+    struct Events {
+        pushed: Vec<(u32, u32)>,
+        expanded: Vec<u32>,
+        relaxed: Vec<(u32, u32, u32)>,
+        path: Option<Vec<u32>>,
+    }
+
+    impl SearchObserver for Events {
+        fn on_push(&mut self, idx: u32, cost: u32) {
+            self.pushed.push((idx, cost));
+        }
+
+        fn on_expand(&mut self, idx: u32, _cost: u32) {
+            self.expanded.push(idx);
+        }
+
+        fn on_relax(&mut self, idx: u32, old_cost: u32, new_cost: u32) {
+            self.relaxed.push((idx, old_cost, new_cost));
+        }
+
+        fn on_path_found(&mut self, path: &[u32]) {
+            self.path = Some(path.to_vec());
+        }
+    }
+
+    #[rustfmt::skip]
+    const GRID: [u32; 18] = [
+        1, 1, 1,
+        1, 0, 1,
+        1, 1, 1,
+
+        1, 1, 1,
+        1, 1, 1,
+        0, 0, 1,
+    ];
+
+    #[test]
+    fn it_reports_search_events() {
+        let up_stairs_idxs = HashSet::from([8]);
+        let down_stairs_idxs = HashSet::from([17]);
+        let neighbors =
+            create_neighbor_idx_cache(&GRID, (3, 3), &up_stairs_idxs, &down_stairs_idxs);
+        let mut events = Events::default();
+        let path = find_path_with_observer(0, 9, |idx| GRID[idx], (3, 3), &neighbors, &mut events);
+        assert_eq!(path, find_path(0, 9, |idx| GRID[idx], (3, 3), &neighbors));
+        assert_eq!(events.path, Some(path));
+        assert_eq!(events.pushed[0], (0, 0));
+        assert_eq!(events.expanded[0], 0);
+        // Every tile is expanded at most once, and only after being pushed.
+        for (i, idx) in events.expanded.iter().enumerate() {
+            assert!(!events.expanded[..i].contains(idx));
+            assert!(events.pushed.iter().any(|(pushed, _)| pushed == idx));
+        }
+    }
+
+    #[test]
+    fn it_reports_relaxed_costs() {
+        // Stepping diagonally costs two, so the corners next to the end are
+        // first reached the long way round through the middle.
+        let grid = [1; 9];
+        let no_stairs = HashSet::new();
+        let neighbors = create_neighbor_idx_cache(&grid, (3, 3), &no_stairs, &no_stairs);
+        let mut events = Events::default();
+        find_path_with_observer(0, 8, |idx| grid[idx], (3, 3), &neighbors, &mut events);
+        assert_eq!(events.relaxed, vec![(6, 6, 4), (2, 6, 4)]);
+        for &(idx, old_cost, new_cost) in &events.relaxed {
+            assert!(events.pushed.contains(&(idx, old_cost)));
+            assert!(events.pushed.contains(&(idx, new_cost)));
+        }
+    }
+
+    #[test]
+    fn it_reports_no_path() {
+        // Without the stairs the second floor can't be reached.
+        let no_stairs = HashSet::new();
+        let neighbors = create_neighbor_idx_cache(&GRID, (3, 3), &no_stairs, &no_stairs);
+        let mut events = Events::default();
+        let path = find_path_with_observer(0, 9, |idx| GRID[idx], (3, 3), &neighbors, &mut events);
+        assert!(path.is_empty());
+        assert_eq!(events.path, None);
+        assert_eq!(events.expanded.len(), 8);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{find_path_with_edge_cost, IndexType, NeighborIdxCache};
+
+/// Movement costs for one kind of agent, indexed by terrain type. `None`
+/// marks terrain the agent cannot enter, as does any terrain type past the
+/// end of `terrain_costs`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AgentProfile {
+    pub terrain_costs: Vec<Option<u32>>,
+}
+
+impl AgentProfile {
+    pub fn new(terrain_costs: Vec<Option<u32>>) -> Self {
+        Self { terrain_costs }
+    }
+
+    #[inline(always)]
+    pub fn get_cost(&self, terrain: u8) -> Option<u32> {
+        self.terrain_costs.get(terrain as usize).copied().flatten()
+    }
+}
+
+/// Like `find_path`, but tile costs come from `profile` by looking up each
+/// tile's type in `terrain`. The neighbor cache should be built with every
+/// tile that any agent can enter open, so one cache serves every profile.
+pub fn find_path_for_agent<N: NeighborIdxCache + ?Sized>(
+    start: N::Index,
+    end: N::Index,
+    dimensions: (u32, u32),
+    neighbors: &N,
+    terrain: &[u8],
+    profile: &AgentProfile,
+) -> Vec<N::Index> {
+    find_path_with_edge_cost(start, end, dimensions, neighbors, |_, idx: N::Index| {
+        profile.get_cost(terrain[idx.index()])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_neighbor_idx_cache;
+    use hashbrown::HashSet;
+
+    const LAND: u8 = 0;
+    const WATER: u8 = 1;
+
+    #[test]
+    fn it_gets_costs_by_terrain_type() {
+        let profile = AgentProfile::new(vec![Some(1), None]);
+        assert_eq!(profile.get_cost(LAND), Some(1));
+        assert_eq!(profile.get_cost(WATER), None);
+        assert_eq!(profile.get_cost(7), None);
+    }
+
+    #[test]
+    fn it_paths_each_agent_over_its_own_terrain() {
+        #[rustfmt::skip]
+        let grid = vec![
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+            1, 1, 1, 1, 1,
+        ];
+        #[rustfmt::skip]
+        let terrain = vec![
+            LAND, LAND, WATER, LAND, LAND,
+            LAND, LAND, WATER, LAND, LAND,
+            LAND, LAND, WATER, LAND, LAND,
+        ];
+        let neighbors = create_neighbor_idx_cache(&grid, (5, 3), &HashSet::new(), &HashSet::new());
+        let walker = AgentProfile::new(vec![Some(1), None]);
+        let boat = AgentProfile::new(vec![None, Some(1)]);
+        let amphibious = AgentProfile::new(vec![Some(1), Some(4)]);
+        let path = find_path_for_agent(0, 10, (5, 3), &neighbors, &terrain, &walker);
+        assert_eq!(path, vec![5, 10]);
+        let path = find_path_for_agent(0, 4, (5, 3), &neighbors, &terrain, &walker);
+        assert_eq!(path, vec![]);
+        let path = find_path_for_agent(2, 12, (5, 3), &neighbors, &terrain, &boat);
+        assert_eq!(path, vec![7, 12]);
+        let path = find_path_for_agent(2, 4, (5, 3), &neighbors, &terrain, &boat);
+        assert_eq!(path, vec![]);
+        let path = find_path_for_agent(0, 4, (5, 3), &neighbors, &terrain, &amphibious);
+        assert_eq!(path, vec![1, 2, 3, 4]);
+    }
+}
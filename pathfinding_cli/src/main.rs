@@ -0,0 +1,258 @@
+mod map;
+mod output;
+mod queries;
+
+use astar_2d::moving_ai::parse_scenarios;
+use map::LoadedMap;
+use queries::{Pathfinder, Query, Variant};
+use std::process::ExitCode;
+use std::time::Instant;
+
+const USAGE: &str = "\
+Usage: pathfind MAP [OPTIONS] [QUERY]...
+
+Loads an ASCII map, with floors separated by blank lines, or a Moving AI .map
+file and runs queries against it. Tiles are written x,y or x,y,floor.
+
+Queries:
+  path START END       Finds the cheapest path from START to END.
+  flow-field GOAL      Points every tile along its cheapest path to GOAL.
+  reachable START      Lists every tile that can be reached from START.
+With no queries, a path is found between the map's S and E tiles.
+
+Options:
+  --scenarios FILE     Finds a path for every scenario in a Moving AI .scen file,
+                       by the benchmarks' rules whatever the variant.
+  --variant VARIANT    How path queries find neighbors: on-the-fly (default),
+                       cached or compact.
+  --cardinal           Moves in 4 directions rather than 8, on single floor maps.
+  --format FORMAT      text (default) or json.
+  --render             Draws the map with each result on it, in text output.
+  -h, --help           Prints this message.
+";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Args {
+    map_path: String,
+    scenarios_path: Option<String>,
+    variant: Variant,
+    cardinal_directions: bool,
+    json: bool,
+    render: bool,
+    queries: Vec<String>,
+}
+
+// Returns `None` when help was asked for.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    let mut map_path = None;
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .ok_or_else(|| format!("`{}` needs a value", option))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--scenarios" => parsed.scenarios_path = Some(value(&arg)?),
+            "--variant" => parsed.variant = value(&arg)?.parse()?,
+            "--cardinal" => parsed.cardinal_directions = true,
+            "--format" => {
+                parsed.json = match value(&arg)?.as_str() {
+                    "text" => false,
+                    "json" => true,
+                    format => {
+                        return Err(format!(
+                            "unknown format `{}`, expected text or json",
+                            format
+                        ))
+                    }
+                }
+            }
+            "--render" => parsed.render = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ if map_path.is_none() => map_path = Some(arg),
+            _ => parsed.queries.push(arg),
+        }
+    }
+    parsed.map_path = map_path.ok_or("no map given")?;
+    Ok(Some(parsed))
+}
+
+fn parse_queries(map: &LoadedMap, words: &[String]) -> Result<Vec<Query>, String> {
+    let mut queries = vec![];
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        let mut tile = || {
+            words
+                .next()
+                .ok_or_else(|| format!("`{}` is missing a tile", word))
+                .and_then(|tile| map.parse_tile(tile))
+        };
+        queries.push(match word.as_str() {
+            "path" => Query::Path {
+                start: tile()?,
+                end: tile()?,
+                optimal_length: None,
+            },
+            "flow-field" => Query::FlowField { goal: tile()? },
+            "reachable" => Query::Reachable { start: tile()? },
+            _ => return Err(format!("unknown query `{}`", word)),
+        });
+    }
+    Ok(queries)
+}
+
+fn run(args: Args) -> Result<String, String> {
+    let read = |path: &str| {
+        std::fs::read_to_string(path).map_err(|error| format!("can't read {}: {}", path, error))
+    };
+    let map = LoadedMap::parse(&read(&args.map_path)?)
+        .map_err(|error| format!("can't load {}: {}", args.map_path, error))?;
+    if args.cardinal_directions && !map.is_flat() {
+        return Err("--cardinal only works on maps with a single floor".to_string());
+    }
+    let mut queries = parse_queries(&map, &args.queries)?;
+    if let Some(path) = &args.scenarios_path {
+        if !map.is_moving_ai || args.cardinal_directions {
+            return Err(
+                "--scenarios only works on Moving AI maps, moving in 8 directions".to_string(),
+            );
+        }
+        let scenarios = parse_scenarios(&read(path)?)
+            .map_err(|error| format!("can't load {}: {}", path, error))?;
+        for scenario in scenarios {
+            if (scenario.map_width, scenario.map_height) != map.tiles.dimensions {
+                return Err(format!(
+                    "{} is for a {}x{} map",
+                    path, scenario.map_width, scenario.map_height
+                ));
+            }
+            queries.push(Query::Path {
+                start: scenario.start,
+                end: scenario.end,
+                optimal_length: Some(scenario.optimal_length),
+            });
+        }
+    }
+    if queries.is_empty() {
+        match (map.tiles.start, map.tiles.end) {
+            (Some(start), Some(end)) => queries.push(Query::Path {
+                start,
+                end,
+                optimal_length: None,
+            }),
+            _ => return Err("no queries given and the map has no S and E tiles".to_string()),
+        }
+    }
+    let started = Instant::now();
+    let pathfinder = Pathfinder::new(&map, args.variant, args.cardinal_directions);
+    let setup_time = started.elapsed();
+    let results: Vec<_> = queries
+        .into_iter()
+        .map(|query| pathfinder.run(query))
+        .collect();
+    Ok(match args.json {
+        true => output::write_json(&map, args.variant, setup_time, &results),
+        _ => output::write_text(&map, &results, args.render),
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprint!("error: {}\n\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(output) => {
+            print!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use astar_2d::moving_ai::LENGTH_TOLERANCE;
+
+    fn args(text: &str) -> Result<Option<Args>, String> {
+        parse_args(text.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn it_parses_args() {
+        assert_eq!(
+            args("map.txt --variant compact path 0,0 1,1 --format json --render"),
+            Ok(Some(Args {
+                map_path: "map.txt".to_string(),
+                variant: Variant::Compact,
+                json: true,
+                render: true,
+                queries: vec!["path".to_string(), "0,0".to_string(), "1,1".to_string()],
+                ..Args::default()
+            }))
+        );
+        assert_eq!(args("map.txt --help"), Ok(None));
+        assert!(args("map.txt --variant fast").is_err());
+        assert!(args("--cardinal").is_err());
+    }
+
+    #[test]
+    fn it_parses_queries() {
+        let map = LoadedMap::parse("...\n...\n").unwrap();
+        let words: Vec<String> = "path 0,0 2,1 flow-field 1,1 reachable 2,0"
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            parse_queries(&map, &words),
+            Ok(vec![
+                Query::Path {
+                    start: 0,
+                    end: 5,
+                    optimal_length: None
+                },
+                Query::FlowField { goal: 4 },
+                Query::Reachable { start: 2 },
+            ])
+        );
+        assert!(parse_queries(&map, &words[..2]).is_err());
+    }
+
+    #[test]
+    fn it_reproduces_the_scenario_lengths() {
+        let maps = concat!(env!("CARGO_MANIFEST_DIR"), "/../astar_2d/maps/");
+        for variant in [Variant::OnTheFly, Variant::Cached, Variant::Compact] {
+            let output = run(Args {
+                map_path: format!("{}rooms.map", maps),
+                scenarios_path: Some(format!("{}rooms.map.scen", maps)),
+                variant,
+                ..Args::default()
+            })
+            .unwrap();
+            let lengths: Vec<(f64, f64)> = output
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("length "))
+                .map(|line| {
+                    let (length, optimal_length) = line.split_once(", optimal ").unwrap();
+                    (length.parse().unwrap(), optimal_length.parse().unwrap())
+                })
+                .collect();
+            assert_eq!(lengths.len(), 24);
+            for (length, optimal_length) in lengths {
+                assert!((length - optimal_length).abs() <= LENGTH_TOLERANCE);
+            }
+        }
+    }
+}
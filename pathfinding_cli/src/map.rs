@@ -0,0 +1,122 @@
+use astar_2d::moving_ai;
+use astar_3d::ascii::AsciiMap;
+use std::collections::HashSet;
+
+/// A map loaded from either format, with any floors stacked one after another
+/// the way astar_3d expects them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadedMap {
+    pub tiles: AsciiMap,
+    /// Whether the map came from a Moving AI `.map` file, which scenarios are
+    /// written for.
+    pub is_moving_ai: bool,
+}
+
+impl LoadedMap {
+    /// Parses a Moving AI `.map` file, told apart by its `type` header, or
+    /// else an ASCII map as described in `astar_3d::ascii`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.trim_start().starts_with("type ") {
+            let map: moving_ai::Map = text.parse().map_err(|error| format!("{}", error))?;
+            return Ok(LoadedMap {
+                tiles: AsciiMap {
+                    grid: map.grid,
+                    dimensions: (map.width, map.height),
+                    up_stairs_idxs: HashSet::new(),
+                    down_stairs_idxs: HashSet::new(),
+                    start: None,
+                    end: None,
+                },
+                is_moving_ai: true,
+            });
+        }
+        Ok(LoadedMap {
+            tiles: text.parse().map_err(|error| format!("{}", error))?,
+            is_moving_ai: false,
+        })
+    }
+
+    pub fn tile_count_per_floor(&self) -> u32 {
+        self.tiles.dimensions.0 * self.tiles.dimensions.1
+    }
+
+    pub fn floor_count(&self) -> u32 {
+        self.tiles.grid.len() as u32 / self.tile_count_per_floor()
+    }
+
+    /// Maps with one floor and no stairs are searched with the 2D crates.
+    pub fn is_flat(&self) -> bool {
+        self.floor_count() == 1
+            && self.tiles.up_stairs_idxs.is_empty()
+            && self.tiles.down_stairs_idxs.is_empty()
+    }
+
+    pub fn to_xyz(&self, idx: u32) -> (u32, u32, u32) {
+        let width = self.tiles.dimensions.0;
+        let tile_count_per_floor = self.tile_count_per_floor();
+        (
+            idx % width,
+            idx % tile_count_per_floor / width,
+            idx / tile_count_per_floor,
+        )
+    }
+
+    /// Parses a tile written as `x,y` or `x,y,floor`.
+    pub fn parse_tile(&self, text: &str) -> Result<u32, String> {
+        let coords = text
+            .split(',')
+            .map(|coord| coord.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| format!("`{}` is not a tile, expected x,y or x,y,floor", text))?;
+        let (x, y, floor) = match coords[..] {
+            [x, y] => (x, y, 0),
+            [x, y, floor] => (x, y, floor),
+            _ => {
+                return Err(format!(
+                    "`{}` is not a tile, expected x,y or x,y,floor",
+                    text
+                ))
+            }
+        };
+        let (width, height) = self.tiles.dimensions;
+        if x >= width || y >= height || floor >= self.floor_count() {
+            return Err(format!("`{}` is outside the map", text));
+        }
+        Ok(floor * self.tile_count_per_floor() + y * width + x)
+    }
+
+    pub fn format_tile(&self, idx: u32) -> String {
+        let (x, y, floor) = self.to_xyz(idx);
+        match self.is_flat() {
+            true => format!("({},{})", x, y),
+            _ => format!("({},{},{})", x, y, floor),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_loads_both_formats() {
+        let map = LoadedMap::parse("type octile\nheight 2\nwidth 2\nmap\n.@\n..\n").unwrap();
+        assert_eq!(map.tiles.grid, vec![1, 0, 1, 1]);
+        assert!(map.is_flat() && map.is_moving_ai);
+        let map = LoadedMap::parse("S.\n.<\n\n.E\n..\n").unwrap();
+        assert_eq!(map.floor_count(), 2);
+        assert!(!map.is_flat() && !map.is_moving_ai);
+        assert_eq!((map.tiles.start, map.tiles.end), (Some(0), Some(5)));
+    }
+
+    #[test]
+    fn it_parses_tiles() {
+        let map = LoadedMap::parse("...\n...\n\n...\n...\n").unwrap();
+        assert_eq!(map.parse_tile("2,1"), Ok(5));
+        assert_eq!(map.parse_tile("2,1,1"), Ok(11));
+        assert_eq!(map.format_tile(11), "(2,1,1)");
+        assert!(map.parse_tile("3,0").is_err());
+        assert!(map.parse_tile("0,0,2").is_err());
+        assert!(map.parse_tile("0").is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use crate::map::LoadedMap;
+use crate::queries::{Answer, Query, QueryResult, Variant};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// One line per result, followed by the map drawn with the result on it when
+/// `render` is set.
+pub fn write_text(map: &LoadedMap, results: &[QueryResult], render: bool) -> String {
+    let mut text = String::new();
+    for result in results {
+        let time = result.time.as_micros();
+        let expansions = result.expansions;
+        match (&result.query, &result.answer) {
+            (
+                Query::Path {
+                    start,
+                    end,
+                    optimal_length,
+                },
+                Answer::Path { path, cost, length },
+            ) => {
+                let (start, end) = (*start, *end);
+                // A search from the end returns `[end]` without taking a step.
+                let steps = match start == end {
+                    true => 0,
+                    _ => path.len(),
+                };
+                match path.is_empty() && start != end {
+                    true => writeln!(
+                        text,
+                        "path {} -> {}: no path, {} expansions, {}µs",
+                        map.format_tile(start),
+                        map.format_tile(end),
+                        expansions,
+                        time
+                    ),
+                    _ => writeln!(
+                        text,
+                        "path {} -> {}: {} steps, cost {}, {} expansions, {}µs",
+                        map.format_tile(start),
+                        map.format_tile(end),
+                        steps,
+                        cost,
+                        expansions,
+                        time
+                    ),
+                }
+                .unwrap();
+                if let Some(optimal_length) = optimal_length {
+                    writeln!(
+                        text,
+                        "  length {:.8}, optimal {:.8}",
+                        length, optimal_length
+                    )
+                    .unwrap();
+                }
+                if !path.is_empty() {
+                    let tiles: Vec<String> = path.iter().map(|&idx| map.format_tile(idx)).collect();
+                    writeln!(text, "  {}", tiles.join(" ")).unwrap();
+                }
+                if render {
+                    text += &map.tiles.render_path(start, path);
+                }
+            }
+            (Query::FlowField { goal }, Answer::FlowField { next }) => {
+                let reaching = next.iter().filter(|next| next.is_some()).count();
+                writeln!(
+                    text,
+                    "flow field to {}: {} tiles reach the goal, {} expansions, {}µs",
+                    map.format_tile(*goal),
+                    reaching,
+                    expansions,
+                    time
+                )
+                .unwrap();
+                if render {
+                    text += &map.tiles.render(|idx| match next[idx as usize] {
+                        _ if idx == *goal => Some('G'),
+                        Some(to) => Some(get_arrow(map, idx, to)),
+                        None => None,
+                    });
+                }
+            }
+            (Query::Reachable { start }, Answer::Reachable { tiles }) => {
+                writeln!(
+                    text,
+                    "reachable from {}: {} tiles, {} expansions, {}µs",
+                    map.format_tile(*start),
+                    tiles.len(),
+                    expansions,
+                    time
+                )
+                .unwrap();
+                if render {
+                    let mut reached = vec![false; map.tiles.grid.len()];
+                    for &idx in tiles {
+                        reached[idx as usize] = true;
+                    }
+                    text += &map.tiles.render(|idx| match idx {
+                        _ if idx == *start => Some('S'),
+                        _ if reached[idx as usize] => Some('*'),
+                        _ => None,
+                    });
+                }
+            }
+            _ => unreachable!("every query is answered in kind"),
+        }
+    }
+    text
+}
+
+// Which way a flow field points from `from` to the adjacent tile `to`, with
+// stairs drawn as `u` and `d`.
+fn get_arrow(map: &LoadedMap, from: u32, to: u32) -> char {
+    let (from_x, from_y, from_floor) = map.to_xyz(from);
+    let (to_x, to_y, to_floor) = map.to_xyz(to);
+    let dx = to_x as i64 - from_x as i64;
+    let dy = to_y as i64 - from_y as i64;
+    match to_floor.cmp(&from_floor) {
+        std::cmp::Ordering::Greater => return 'u',
+        std::cmp::Ordering::Less => return 'd',
+        _ => {}
+    }
+    match (dx.signum(), dy.signum()) {
+        (0, -1) => '↑',
+        (1, -1) => '↗',
+        (1, 0) => '→',
+        (1, 1) => '↘',
+        (0, 1) => '↓',
+        (-1, 1) => '↙',
+        (-1, 0) => '←',
+        _ => '↖',
+    }
+}
+
+/// Every result in one JSON object, with tiles written as `[x, y]` on maps
+/// with a single floor and `[x, y, floor]` otherwise.
+pub fn write_json(
+    map: &LoadedMap,
+    variant: Variant,
+    setup_time: Duration,
+    results: &[QueryResult],
+) -> String {
+    let tile = |idx: u32| {
+        let (x, y, floor) = map.to_xyz(idx);
+        match map.is_flat() {
+            true => format!("[{},{}]", x, y),
+            _ => format!("[{},{},{}]", x, y, floor),
+        }
+    };
+    let tiles = |idxs: &mut dyn Iterator<Item = String>| {
+        format!("[{}]", idxs.collect::<Vec<String>>().join(","))
+    };
+    let results: Vec<String> = results
+        .iter()
+        .map(|result| {
+            let answer = match (&result.query, &result.answer) {
+                (
+                    Query::Path {
+                        start,
+                        end,
+                        optimal_length,
+                    },
+                    Answer::Path { path, cost, length },
+                ) => format!(
+                    "\"query\":\"path\",\"start\":{},\"end\":{},\"found\":{},\"path\":{},\"cost\":{},\"length\":{},\"optimal_length\":{}",
+                    tile(*start),
+                    tile(*end),
+                    !path.is_empty() || start == end,
+                    tiles(&mut path.iter().map(|&idx| tile(idx))),
+                    cost,
+                    length,
+                    optimal_length.map_or("null".to_string(), |length| length.to_string())
+                ),
+                (Query::FlowField { goal }, Answer::FlowField { next }) => format!(
+                    "\"query\":\"flow-field\",\"goal\":{},\"next\":{}",
+                    tile(*goal),
+                    tiles(&mut next.iter().map(|next| next.map_or("null".to_string(), tile)))
+                ),
+                (Query::Reachable { start }, Answer::Reachable { tiles: reached }) => format!(
+                    "\"query\":\"reachable\",\"start\":{},\"tiles\":{}",
+                    tile(*start),
+                    tiles(&mut reached.iter().map(|&idx| tile(idx)))
+                ),
+                _ => unreachable!("every query is answered in kind"),
+            };
+            format!(
+                "{{{},\"expansions\":{},\"time_us\":{}}}",
+                answer,
+                result.expansions,
+                result.time.as_micros()
+            )
+        })
+        .collect();
+    format!(
+        "{{\"map\":{{\"width\":{},\"height\":{},\"floors\":{}}},\"variant\":\"{}\",\"setup_time_us\":{},\"results\":[{}]}}\n",
+        map.tiles.dimensions.0,
+        map.tiles.dimensions.1,
+        map.floor_count(),
+        variant,
+        setup_time.as_micros(),
+        results.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries::Pathfinder;
+
+    #[test]
+    fn it_writes_results() {
+        let map = LoadedMap::parse("S.\n#E\n").unwrap();
+        let pathfinder = Pathfinder::new(&map, Variant::OnTheFly, false);
+        let mut results = vec![
+            pathfinder.run(Query::Path {
+                start: 0,
+                end: 3,
+                optimal_length: None,
+            }),
+            pathfinder.run(Query::Reachable { start: 0 }),
+            pathfinder.run(Query::Path {
+                start: 0,
+                end: 0,
+                optimal_length: None,
+            }),
+        ];
+        for result in &mut results {
+            result.time = Duration::from_micros(5);
+        }
+        assert_eq!(
+            write_text(&map, &results, true),
+            "path (0,0) -> (1,1): 1 steps, cost 3, 1 expansions, 5µs\n  (1,1)\nS.\n#E\n\
+             reachable from (0,0): 3 tiles, 3 expansions, 5µs\nS*\n#*\n\
+             path (0,0) -> (0,0): 0 steps, cost 0, 0 expansions, 5µs\n  (0,0)\nS.\n#.\n"
+        );
+        assert_eq!(
+            write_json(&map, Variant::OnTheFly, Duration::ZERO, &results[..1]),
+            "{\"map\":{\"width\":2,\"height\":2,\"floors\":1},\"variant\":\"on-the-fly\",\
+             \"setup_time_us\":0,\"results\":[{\"query\":\"path\",\"start\":[0,0],\"end\":[1,1],\
+             \"found\":true,\"path\":[[1,1]],\"cost\":3,\"length\":1.4142135623730951,\
+             \"optimal_length\":null,\"expansions\":1,\"time_us\":5}]}\n"
+        );
+        assert_eq!(
+            write_json(&map, Variant::OnTheFly, Duration::ZERO, &results[2..]),
+            "{\"map\":{\"width\":2,\"height\":2,\"floors\":1},\"variant\":\"on-the-fly\",\
+             \"setup_time_us\":0,\"results\":[{\"query\":\"path\",\"start\":[0,0],\"end\":[0,0],\
+             \"found\":true,\"path\":[[0,0]],\"cost\":0,\"length\":0,\
+             \"optimal_length\":null,\"expansions\":0,\"time_us\":5}]}\n"
+        );
+    }
+}
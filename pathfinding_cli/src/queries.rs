@@ -0,0 +1,457 @@
+use crate::map::LoadedMap;
+use astar_2d_cached_neighbors::CompactNeighborIdxCache as CompactNeighborIdxCache2d;
+use astar_3d_cached_neighbors::CompactNeighborIdxCache as CompactNeighborIdxCache3d;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Where path queries get each tile's neighbors from.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Variant {
+    /// astar_2d or astar_3d, working neighbors out from the grid as needed.
+    #[default]
+    OnTheFly,
+    /// The cached crates with a `Vec<Vec<u32>>` neighbor cache.
+    Cached,
+    /// The cached crates with a `CompactNeighborIdxCache`.
+    Compact,
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "on-the-fly" => Ok(Variant::OnTheFly),
+            "cached" => Ok(Variant::Cached),
+            "compact" => Ok(Variant::Compact),
+            _ => Err(format!(
+                "unknown variant `{}`, expected on-the-fly, cached or compact",
+                text
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Variant::OnTheFly => "on-the-fly",
+            Variant::Cached => "cached",
+            Variant::Compact => "compact",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    Path {
+        start: u32,
+        end: u32,
+        /// The length a scenario file gives for the shortest path.
+        optimal_length: Option<f64>,
+    },
+    FlowField {
+        goal: u32,
+    },
+    Reachable {
+        start: u32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Answer {
+    Path {
+        path: Vec<u32>,
+        cost: u64,
+        /// The path's length with diagonal steps counted as √2, as in the
+        /// Moving AI scenarios.
+        length: f64,
+    },
+    /// The next tile on the cheapest path to the goal from every tile that
+    /// can reach it.
+    FlowField {
+        next: Vec<Option<u32>>,
+    },
+    Reachable {
+        tiles: Vec<u32>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryResult {
+    pub query: Query,
+    pub answer: Answer,
+    /// Tiles whose neighbors were looked at.
+    pub expansions: u64,
+    pub time: Duration,
+}
+
+enum Neighbors {
+    OnTheFly,
+    Cached,
+    Compact2d(CompactNeighborIdxCache2d),
+    Compact3d(CompactNeighborIdxCache3d),
+}
+
+/// Runs queries against one map. Flow fields and reachability always walk
+/// `neighbor_idx_cache`; the variant only changes how paths are found.
+pub struct Pathfinder<'a> {
+    map: &'a LoadedMap,
+    cardinal_directions: bool,
+    neighbors: Neighbors,
+    neighbor_idx_cache: Vec<Vec<u32>>,
+}
+
+impl<'a> Pathfinder<'a> {
+    pub fn new(map: &'a LoadedMap, variant: Variant, cardinal_directions: bool) -> Self {
+        let (up_stairs_idxs, down_stairs_idxs) = stairs_for_cached_crates(map);
+        let neighbor_idx_cache = match (map.is_flat(), cardinal_directions) {
+            (true, true) => astar_2d_cached_neighbors::create_cardinal_neighbor_idx_cache(
+                &map.tiles.grid,
+                map.tiles.dimensions.0,
+            ),
+            (true, false) => astar_2d_cached_neighbors::create_neighbor_idx_cache(
+                &map.tiles.grid,
+                map.tiles.dimensions.0,
+            ),
+            _ => astar_3d_cached_neighbors::create_neighbor_idx_cache(
+                &map.tiles.grid,
+                map.tiles.dimensions,
+                &up_stairs_idxs,
+                &down_stairs_idxs,
+            ),
+        };
+        let neighbors = match (variant, map.is_flat()) {
+            (Variant::OnTheFly, _) => Neighbors::OnTheFly,
+            (Variant::Cached, _) => Neighbors::Cached,
+            (Variant::Compact, true) => Neighbors::Compact2d(
+                CompactNeighborIdxCache2d::from_neighbor_idx_cache(&neighbor_idx_cache),
+            ),
+            (Variant::Compact, false) => Neighbors::Compact3d(
+                CompactNeighborIdxCache3d::from_neighbor_idx_cache(&neighbor_idx_cache),
+            ),
+        };
+        Pathfinder {
+            map,
+            cardinal_directions,
+            neighbors,
+            neighbor_idx_cache,
+        }
+    }
+
+    pub fn run(&self, query: Query) -> QueryResult {
+        let started = Instant::now();
+        let (answer, expansions) = match query {
+            Query::Path {
+                start,
+                end,
+                optimal_length: Some(_),
+            } => self.find_octile_path(start, end),
+            Query::Path { start, end, .. } => self.find_path(start, end),
+            Query::FlowField { goal } => self.flow_field(goal),
+            Query::Reachable { start } => self.reachable(start),
+        };
+        QueryResult {
+            query,
+            answer,
+            expansions,
+            time: started.elapsed(),
+        }
+    }
+
+    fn find_path(&self, start: u32, end: u32) -> (Answer, u64) {
+        let map = self.map;
+        let grid = &map.tiles.grid;
+        let (width, _) = map.tiles.dimensions;
+        // Each crate has its own stats type.
+        let (path, expansions) = match (&self.neighbors, map.is_flat()) {
+            (Neighbors::OnTheFly, true) => {
+                let (path, stats) = astar_2d::measure(|observer| {
+                    astar_2d::find_path_with_observer(
+                        start,
+                        end,
+                        grid,
+                        width,
+                        self.cardinal_directions,
+                        observer,
+                    )
+                });
+                (path, stats.expansions)
+            }
+            (Neighbors::OnTheFly, false) => {
+                let (path, stats) = astar_3d::measure(|observer| {
+                    astar_3d::find_path_with_observer(
+                        start,
+                        end,
+                        grid,
+                        map.tiles.dimensions,
+                        &map.tiles.up_stairs_idxs,
+                        &map.tiles.down_stairs_idxs,
+                        observer,
+                    )
+                });
+                (path, stats.expansions)
+            }
+            (Neighbors::Cached, true) => {
+                let (path, stats) = astar_2d_cached_neighbors::measure(|observer| {
+                    astar_2d_cached_neighbors::find_path_with_observer(
+                        start,
+                        end,
+                        grid,
+                        width,
+                        &self.neighbor_idx_cache,
+                        observer,
+                    )
+                });
+                (path, stats.expansions)
+            }
+            (Neighbors::Cached, false) => {
+                let (path, stats) = astar_3d_cached_neighbors::measure(|observer| {
+                    astar_3d_cached_neighbors::find_path_with_observer(
+                        start,
+                        end,
+                        grid,
+                        map.tiles.dimensions,
+                        &self.neighbor_idx_cache,
+                        observer,
+                    )
+                });
+                (path, stats.expansions)
+            }
+            (Neighbors::Compact2d(neighbors), _) => {
+                let (path, stats) = astar_2d_cached_neighbors::measure(|observer| {
+                    astar_2d_cached_neighbors::find_path_with_observer(
+                        start, end, grid, width, neighbors, observer,
+                    )
+                });
+                (path, stats.expansions)
+            }
+            (Neighbors::Compact3d(neighbors), _) => {
+                let (path, stats) = astar_3d_cached_neighbors::measure(|observer| {
+                    astar_3d_cached_neighbors::find_path_with_observer(
+                        start,
+                        end,
+                        grid,
+                        map.tiles.dimensions,
+                        neighbors,
+                        observer,
+                    )
+                });
+                (path, stats.expansions)
+            }
+        };
+        (self.path_answer(start, path), expansions)
+    }
+
+    // Scenarios give the length of the shortest path by the Moving AI rules,
+    // so their paths are found by those rules whatever the variant.
+    fn find_octile_path(&self, start: u32, end: u32) -> (Answer, u64) {
+        let (width, _) = self.map.tiles.dimensions;
+        let (path, stats) = astar_2d::measure(|observer| {
+            astar_2d::moving_ai::find_octile_path_with_observer(
+                start,
+                end,
+                &self.map.tiles.grid,
+                width,
+                observer,
+            )
+        });
+        (self.path_answer(start, path), stats.expansions)
+    }
+
+    fn path_answer(&self, start: u32, path: Vec<u32>) -> Answer {
+        let (width, _) = self.map.tiles.dimensions;
+        Answer::Path {
+            cost: path_cost(self.map, start, &path),
+            length: astar_2d::moving_ai::path_length(start, &path, width),
+            path,
+        }
+    }
+
+    // Dijkstra's algorithm outward from the goal, following steps backwards.
+    fn flow_field(&self, goal: u32) -> (Answer, u64) {
+        let grid = &self.map.tiles.grid;
+        let mut reverse_neighbors = vec![vec![]; grid.len()];
+        for (from, neighbors) in self.neighbor_idx_cache.iter().enumerate() {
+            for &to in neighbors {
+                reverse_neighbors[to as usize].push(from as u32);
+            }
+        }
+        let mut cost_to_goal: Vec<Option<u64>> = vec![None; grid.len()];
+        let mut next = vec![None; grid.len()];
+        let mut closed = vec![false; grid.len()];
+        let mut frontier = BinaryHeap::new();
+        let mut expansions = 0;
+        cost_to_goal[goal as usize] = Some(0);
+        frontier.push(Reverse((0, goal)));
+        while let Some(Reverse((cost, to))) = frontier.pop() {
+            if std::mem::replace(&mut closed[to as usize], true) {
+                continue;
+            }
+            expansions += 1;
+            for &from in &reverse_neighbors[to as usize] {
+                let cost = cost + step_cost(self.map, from, to);
+                if cost_to_goal[from as usize].map_or(true, |old| cost < old) {
+                    cost_to_goal[from as usize] = Some(cost);
+                    next[from as usize] = Some(to);
+                    frontier.push(Reverse((cost, from)));
+                }
+            }
+        }
+        (Answer::FlowField { next }, expansions)
+    }
+
+    fn reachable(&self, start: u32) -> (Answer, u64) {
+        let mut seen = vec![false; self.map.tiles.grid.len()];
+        let mut tiles = vec![start];
+        let mut frontier = VecDeque::from([start]);
+        seen[start as usize] = true;
+        while let Some(current) = frontier.pop_front() {
+            for &neighbor in &self.neighbor_idx_cache[current as usize] {
+                if !std::mem::replace(&mut seen[neighbor as usize], true) {
+                    tiles.push(neighbor);
+                    frontier.push_back(neighbor);
+                }
+            }
+        }
+        let expansions = tiles.len() as u64;
+        (Answer::Reachable { tiles }, expansions)
+    }
+}
+
+fn stairs_for_cached_crates(map: &LoadedMap) -> (hashbrown::HashSet<u32>, hashbrown::HashSet<u32>) {
+    (
+        map.tiles.up_stairs_idxs.iter().copied().collect(),
+        map.tiles.down_stairs_idxs.iter().copied().collect(),
+    )
+}
+
+/// What the searches charge for a step: the cost of the tile entered plus the
+/// manhattan distance moved.
+fn step_cost(map: &LoadedMap, from: u32, to: u32) -> u64 {
+    let (from_x, from_y, from_floor) = map.to_xyz(from);
+    let (to_x, to_y, to_floor) = map.to_xyz(to);
+    map.tiles.grid[to as usize] as u64
+        + (from_x.abs_diff(to_x) + from_y.abs_diff(to_y) + from_floor.abs_diff(to_floor)) as u64
+}
+
+fn path_cost(map: &LoadedMap, start: u32, path: &[u32]) -> u64 {
+    let mut from = start;
+    let mut cost = 0;
+    // A search from the end returns `[end]` without taking a step.
+    for &to in path.iter().filter(|&&to| to != start) {
+        cost += step_cost(map, from, to);
+        from = to;
+    }
+    cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLS: &str = "
+        S......
+        ..#..#.
+        ..##.#.
+        ..#..#.
+        ..####.
+        .......
+        ......E
+    ";
+
+    const FLOORS: &str = "
+        S.#.
+        .#..
+        ..#<
+
+        ....
+        .##.
+        E..>
+    ";
+
+    fn path_query(map: &LoadedMap) -> Query {
+        Query::Path {
+            start: map.tiles.start.unwrap(),
+            end: map.tiles.end.unwrap(),
+            optimal_length: None,
+        }
+    }
+
+    #[test]
+    fn it_finds_the_same_path_with_every_variant() {
+        for text in [WALLS, FLOORS] {
+            let map = LoadedMap::parse(text).unwrap();
+            let answers: Vec<Answer> = [Variant::OnTheFly, Variant::Cached, Variant::Compact]
+                .into_iter()
+                .map(|variant| {
+                    Pathfinder::new(&map, variant, false)
+                        .run(path_query(&map))
+                        .answer
+                })
+                .collect();
+            assert_eq!(answers[0], answers[1]);
+            assert_eq!(answers[0], answers[2]);
+        }
+    }
+
+    #[test]
+    fn it_measures_paths() {
+        let map = LoadedMap::parse(WALLS).unwrap();
+        let result = Pathfinder::new(&map, Variant::OnTheFly, true).run(path_query(&map));
+        let expected = astar_2d::find_path(0, 48, &map.tiles.grid, 7, true);
+        assert_eq!(
+            result.answer,
+            Answer::Path {
+                cost: 2 * expected.len() as u64,
+                length: expected.len() as f64,
+                path: expected,
+            }
+        );
+        assert!(result.expansions > 0);
+    }
+
+    #[test]
+    fn it_builds_flow_fields() {
+        let map = LoadedMap::parse(FLOORS).unwrap();
+        let pathfinder = Pathfinder::new(&map, Variant::Cached, false);
+        let goal = map.tiles.end.unwrap();
+        let next = match pathfinder.run(Query::FlowField { goal }).answer {
+            Answer::FlowField { next } => next,
+            answer => panic!("expected a flow field, got {:?}", answer),
+        };
+        assert_eq!(next[goal as usize], None);
+        // Following the field from the start costs the same as the path.
+        let mut current = map.tiles.start.unwrap();
+        let mut followed = vec![];
+        while let Some(step) = next[current as usize] {
+            followed.push(step);
+            current = step;
+        }
+        let path = match pathfinder.run(path_query(&map)).answer {
+            Answer::Path { path, .. } => path,
+            answer => panic!("expected a path, got {:?}", answer),
+        };
+        assert_eq!(current, goal);
+        assert_eq!(
+            path_cost(&map, map.tiles.start.unwrap(), &followed),
+            path_cost(&map, map.tiles.start.unwrap(), &path)
+        );
+    }
+
+    #[test]
+    fn it_finds_reachable_tiles() {
+        let map = LoadedMap::parse("S.#.\n..#.\n").unwrap();
+        let result =
+            Pathfinder::new(&map, Variant::OnTheFly, false).run(Query::Reachable { start: 0 });
+        assert_eq!(
+            result.answer,
+            Answer::Reachable {
+                tiles: vec![0, 1, 4, 5]
+            }
+        );
+    }
+}